@@ -0,0 +1,12 @@
+-- every fortuna block observed by the BlockService, so stored proofs can be re-derived later
+CREATE TABLE blocks(
+    block_number INTEGER PRIMARY KEY NOT NULL,
+    current_hash TEXT CHECK(length(current_hash) = 64) NOT NULL,
+    leading_zeroes INTEGER NOT NULL,
+    difficulty_number INTEGER NOT NULL,
+    epoch_time BIGINT NOT NULL,
+    current_posix_time BIGINT NOT NULL,
+    transaction_id TEXT NOT NULL,
+    output_index BIGINT NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,22 @@
+-- what each miner was owed for each datum the pool submitted
+CREATE TABLE payouts(
+    id SERIAL PRIMARY KEY NOT NULL,
+    transaction_hash TEXT CHECK(length(transaction_hash) = 64) NOT NULL,
+    miner_id INTEGER NOT NULL,
+    address TEXT NOT NULL,
+    pkh TEXT NOT NULL,
+    share_count BIGINT NOT NULL,
+    estimated_hashes DOUBLE PRECISION NOT NULL,
+    amount BIGINT NOT NULL,
+    pool_fee BIGINT NOT NULL,
+    finders_fee BIGINT NOT NULL,
+    status TEXT CHECK(status IN ('pending', 'confirmed', 'rejected')) NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY(transaction_hash) REFERENCES datum_submissions(transaction_hash),
+    FOREIGN KEY(miner_id) REFERENCES miners(id)
+);
+
+CREATE INDEX idx_payouts_transaction_hash ON payouts(transaction_hash);
+CREATE INDEX idx_payouts_miner_id ON payouts(miner_id);
+CREATE INDEX idx_payouts_pkh ON payouts(pkh);
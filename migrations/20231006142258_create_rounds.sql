@@ -0,0 +1,30 @@
+-- a round collects shares until a datum paying them out is submitted
+CREATE TABLE rounds(
+    id SERIAL PRIMARY KEY NOT NULL,
+    status TEXT CHECK(status IN ('open', 'pending', 'confirmed', 'rejected')) NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP,
+    transaction_hash TEXT,
+    FOREIGN KEY(transaction_hash) REFERENCES datum_submissions(transaction_hash)
+);
+
+-- only one round is ever collecting shares
+CREATE UNIQUE INDEX idx_rounds_open ON rounds(status) WHERE status = 'open';
+CREATE INDEX idx_rounds_transaction_hash ON rounds(transaction_hash);
+
+ALTER TABLE proof_of_work
+ADD COLUMN round_id INTEGER REFERENCES rounds(id);
+
+CREATE INDEX idx_pow_round_id ON proof_of_work(round_id);
+
+-- everything since the last confirmed datum is still owed, so it becomes the first open round
+INSERT INTO rounds (status, started_at)
+SELECT 'open', COALESCE(
+    (SELECT MAX(confirmed_at) FROM datum_submissions),
+    (SELECT MIN(created_at) FROM proof_of_work),
+    NOW()
+);
+
+UPDATE proof_of_work
+SET round_id = (SELECT id FROM rounds WHERE status = 'open')
+WHERE created_at >= (SELECT started_at FROM rounds WHERE status = 'open');
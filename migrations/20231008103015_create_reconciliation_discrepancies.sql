@@ -0,0 +1,17 @@
+-- set once a confirmed datum's bank update has been compared against its payouts
+ALTER TABLE datum_submissions
+ADD COLUMN reconciled_at TIMESTAMP;
+
+-- differences between what tunapond paid into the bank for a datum and what the bank datum on chain shows
+CREATE TABLE reconciliation_discrepancies(
+    id SERIAL PRIMARY KEY NOT NULL,
+    transaction_hash TEXT CHECK(length(transaction_hash) = 64) NOT NULL,
+    pkh TEXT,
+    expected BIGINT,
+    actual BIGINT,
+    reason TEXT CHECK(reason IN ('amount_mismatch', 'bank_not_found')) NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    FOREIGN KEY(transaction_hash) REFERENCES datum_submissions(transaction_hash)
+);
+
+CREATE INDEX idx_reconciliation_discrepancies_transaction_hash ON reconciliation_discrepancies(transaction_hash);
@@ -0,0 +1,19 @@
+-- TUNA taken out of the pool bank by its owners, found by following spends of the BANK UTxO
+CREATE TABLE withdrawals(
+    id SERIAL PRIMARY KEY NOT NULL,
+    transaction_hash TEXT CHECK(length(transaction_hash) = 64) NOT NULL,
+    pkh TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    slot_no INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    UNIQUE(transaction_hash, pkh)
+);
+
+CREATE INDEX idx_withdrawals_pkh ON withdrawals(pkh);
+
+-- how far each background watcher has got, so it can pick up where it left off
+CREATE TABLE watermarks(
+    name TEXT PRIMARY KEY NOT NULL,
+    value BIGINT NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,10 @@
+-- solo miners use the pool's infrastructure but are paid the whole reward, less the pool fee, for blocks they find
+ALTER TABLE miners
+ADD COLUMN solo BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- solo shares never join a round, so they are not part of anyone else's split
+ALTER TABLE proof_of_work
+ADD COLUMN solo BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE payouts
+ADD COLUMN solo BOOLEAN NOT NULL DEFAULT FALSE;
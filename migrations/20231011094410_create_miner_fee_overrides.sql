@@ -0,0 +1,16 @@
+-- fee policies that replace the pool's own for particular miners, e.g. partners
+CREATE TABLE miner_fee_overrides(
+    miner_id INTEGER PRIMARY KEY NOT NULL,
+    policy TEXT NOT NULL,
+    note TEXT,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY(miner_id) REFERENCES miners(id)
+);
+
+-- the fee policy each payout's pool fee was charged under
+ALTER TABLE payouts
+ADD COLUMN fee_policy TEXT;
+
+ALTER TABLE payouts
+ADD COLUMN fee_override BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,17 @@
+-- where a miner's rewards are credited, when it is not the address they mine with
+ALTER TABLE miners
+ADD COLUMN payout_address TEXT;
+
+-- every change of payout address, with the signature from the mining key that authorized it
+CREATE TABLE payout_address_changes(
+    id SERIAL PRIMARY KEY NOT NULL,
+    miner_id INTEGER NOT NULL,
+    change_number INTEGER NOT NULL,
+    previous_address TEXT,
+    payout_address TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    key TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    FOREIGN KEY(miner_id) REFERENCES miners(id),
+    UNIQUE(miner_id, change_number)
+);
@@ -0,0 +1,25 @@
+-- a miner's rewards shared between several payout addresses, in basis points summing to 10000
+CREATE TABLE payout_splits(
+    miner_id INTEGER NOT NULL,
+    address TEXT NOT NULL,
+    basis_points INTEGER CHECK(basis_points > 0 AND basis_points <= 10000) NOT NULL,
+    PRIMARY KEY (miner_id, address),
+    FOREIGN KEY(miner_id) REFERENCES miners(id)
+);
+
+-- every change of a miner's splits, with the signature from the mining key that authorized it
+CREATE TABLE payout_split_changes(
+    id SERIAL PRIMARY KEY NOT NULL,
+    miner_id INTEGER NOT NULL,
+    change_number INTEGER NOT NULL,
+    splits TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    key TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    FOREIGN KEY(miner_id) REFERENCES miners(id),
+    UNIQUE(miner_id, change_number)
+);
+
+-- the miner's share of the payout this address received, when their rewards were split
+ALTER TABLE payouts
+ADD COLUMN split_basis_points INTEGER;
@@ -0,0 +1,17 @@
+-- the worker a share was mined by, when the miner names one
+ALTER TABLE proof_of_work
+ADD COLUMN worker TEXT;
+
+-- shares turned away at submission, counted per submission and reason
+CREATE TABLE share_rejections(
+    id SERIAL PRIMARY KEY NOT NULL,
+    miner_id INTEGER NOT NULL,
+    worker TEXT,
+    block_number INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    share_count INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    FOREIGN KEY(miner_id) REFERENCES miners(id)
+);
+
+CREATE INDEX idx_share_rejections_miner_id_created_at ON share_rejections(miner_id, created_at);
@@ -0,0 +1,26 @@
+-- shares rolled up per miner, worker and bucket of time, kept after the proofs themselves are cleaned up
+CREATE TABLE share_aggregates(
+    miner_id INTEGER NOT NULL,
+    -- empty for shares mined without naming a worker
+    worker TEXT NOT NULL,
+    bucket_start TIMESTAMP NOT NULL,
+    sampling_difficulty INTEGER NOT NULL,
+    share_count BIGINT NOT NULL,
+    PRIMARY KEY (miner_id, worker, bucket_start, sampling_difficulty),
+    FOREIGN KEY(miner_id) REFERENCES miners(id)
+);
+
+CREATE INDEX idx_share_aggregates_bucket_start ON share_aggregates(bucket_start);
+
+-- rejected shares rolled up the same way, by reason
+CREATE TABLE share_rejection_aggregates(
+    miner_id INTEGER NOT NULL,
+    worker TEXT NOT NULL,
+    bucket_start TIMESTAMP NOT NULL,
+    reason TEXT NOT NULL,
+    share_count BIGINT NOT NULL,
+    PRIMARY KEY (miner_id, worker, bucket_start, reason),
+    FOREIGN KEY(miner_id) REFERENCES miners(id)
+);
+
+CREATE INDEX idx_share_rejection_aggregates_bucket_start ON share_rejection_aggregates(bucket_start);
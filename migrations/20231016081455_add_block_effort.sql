@@ -0,0 +1,5 @@
+-- the hashes the pool put in since its previous datum, and the hashes the block's target was expected to take
+-- both stay null for datums submitted before effort was tracked
+ALTER TABLE datum_submissions
+ADD COLUMN estimated_hashes DOUBLE PRECISION,
+ADD COLUMN expected_hashes DOUBLE PRECISION;
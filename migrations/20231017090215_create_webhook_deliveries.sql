@@ -0,0 +1,16 @@
+-- an outbox of webhook payloads, one row per event per url, retried until delivered or abandoned
+CREATE TABLE webhook_deliveries(
+    id SERIAL PRIMARY KEY NOT NULL,
+    url TEXT NOT NULL,
+    event TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL,
+    last_error TEXT,
+    delivered_at TIMESTAMP,
+    abandoned_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_webhook_deliveries_due ON webhook_deliveries(next_attempt_at)
+WHERE delivered_at IS NULL AND abandoned_at IS NULL;
@@ -0,0 +1,3 @@
+-- banned miners have their shares turned away
+ALTER TABLE miners
+ADD COLUMN banned BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,5 @@
+-- the block a round was cut for, recorded before its datum is submitted, so a submission that never got
+-- as far as recording its transaction can still be told apart from one that made it on chain
+ALTER TABLE rounds
+ADD COLUMN sha TEXT,
+ADD COLUMN block_number INTEGER;
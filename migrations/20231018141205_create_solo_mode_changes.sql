@@ -0,0 +1,16 @@
+-- every change between solo and pooled mining, with the signature from the mining key that authorized it.
+-- A change takes effect when the next block is seen, so a miner cannot switch while holding a block.
+CREATE TABLE solo_mode_changes(
+    id SERIAL PRIMARY KEY NOT NULL,
+    miner_id INTEGER NOT NULL,
+    change_number INTEGER NOT NULL,
+    solo BOOLEAN NOT NULL,
+    signature TEXT NOT NULL,
+    key TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    applied_at TIMESTAMP,
+    FOREIGN KEY(miner_id) REFERENCES miners(id),
+    UNIQUE(miner_id, change_number)
+);
+
+CREATE INDEX idx_solo_mode_changes_unapplied ON solo_mode_changes(miner_id) WHERE applied_at IS NULL;
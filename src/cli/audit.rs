@@ -0,0 +1,47 @@
+use sqlx::{Pool, Postgres};
+
+use crate::service::audit::{audit_proofs, AuditFailureReason};
+
+use super::{flag_value, parse_flag, resolve_miner};
+
+// tunapond audit [--from <block_number>] [--to <block_number>] [--miner <address|miner_id>]
+pub async fn run(pool: &Pool<Postgres>, args: &[String]) -> Result<(), String> {
+    let from_block: i32 = parse_flag(args, "--from")?.unwrap_or(0);
+    let to_block: i32 = parse_flag(args, "--to")?.unwrap_or(i32::MAX);
+
+    let miner_id = match flag_value(args, "--miner") {
+        Some(miner) => Some(resolve_miner(pool, &miner).await?),
+        None => None,
+    };
+
+    let report = audit_proofs(pool, miner_id, from_block, to_block)
+        .await
+        .map_err(|e| format!("Failed to audit proofs of work: {:?}", e))?;
+
+    for failure in report.failures.iter() {
+        let reason = match &failure.reason {
+            AuditFailureReason::MissingBlock => String::from("block was never recorded, cannot re-derive target state"),
+            AuditFailureReason::MalformedNonce => String::from("stored nonce is not 16 bytes of hex"),
+            AuditFailureReason::ShaMismatch { recomputed } => format!("sha does not reproduce, recomputed {}", recomputed),
+            AuditFailureReason::NonceNotAssigned => String::from("nonce suffix does not match the miner and pool"),
+            AuditFailureReason::InsufficientDifficulty { leading_zeroes, required } => {
+                format!("sha has {} leading zeroes but sampling difficulty was {}", leading_zeroes, required)
+            }
+        };
+
+        println!(
+            "block {} miner {} ({}) at {} sha {} nonce {}: {}",
+            failure.proof.block_number,
+            failure.proof.miner_id,
+            failure.proof.miner_address,
+            failure.proof.created_at,
+            failure.proof.sha,
+            failure.proof.nonce,
+            reason
+        );
+    }
+
+    println!("Checked {} proofs of work, {} did not reproduce.", report.checked, report.failures.len());
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use sqlx::{Pool, Postgres};
+
+use crate::{address, model::miner::get_miner_by_pkh};
+
+pub mod audit;
+pub mod simulate;
+
+pub async fn run(pool: &Pool<Postgres>, command: &str, args: &[String]) -> Result<(), String> {
+    match command {
+        "audit" => audit::run(pool, args).await,
+        "simulate" => simulate::run(pool, args).await,
+        _ => Err(format!("Unknown command {}. Available commands: audit, simulate", command)),
+    }
+}
+
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .cloned()
+}
+
+fn parse_flag<T: std::str::FromStr>(args: &[String], flag: &str) -> Result<Option<T>, String> {
+    match flag_value(args, flag) {
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("Invalid value {} for {}", value, flag)),
+        None => Ok(None),
+    }
+}
+
+/// Accepts either a numeric miner id or a bech32 address.
+async fn resolve_miner(pool: &Pool<Postgres>, miner: &str) -> Result<i32, String> {
+    if let Ok(miner_id) = miner.parse::<i32>() {
+        return Ok(miner_id);
+    }
+
+    let pkh = address::pkh_from_address(miner).map_err(|e| e.to_string())?;
+
+    get_miner_by_pkh(pool, &pkh)
+        .await
+        .map_err(|e| format!("Failed to look up miner: {:?}", e))?
+        .map(|miner| miner.id)
+        .ok_or(format!("No miner found for address {}", miner))
+}
@@ -0,0 +1,61 @@
+use sqlx::{Pool, Postgres};
+
+use crate::service::{
+    reward::{RewardScheme, DEFAULT_PPLNS_WINDOW, DEFAULT_SCORE_HALF_LIFE_SECONDS},
+    simulation::simulate,
+};
+
+use super::parse_flag;
+
+// tunapond simulate [--from <block_number>] [--to <block_number>] [--window <shares>] [--half-life <seconds>]
+pub async fn run(pool: &Pool<Postgres>, args: &[String]) -> Result<(), String> {
+    let from_block: i32 = parse_flag(args, "--from")?.unwrap_or(0);
+    let to_block: i32 = parse_flag(args, "--to")?.unwrap_or(i32::MAX);
+    let window: i64 = parse_flag(args, "--window")?.unwrap_or(DEFAULT_PPLNS_WINDOW);
+    let half_life_seconds: f64 = parse_flag(args, "--half-life")?.unwrap_or(DEFAULT_SCORE_HALF_LIFE_SECONDS);
+
+    let schemes = vec![
+        RewardScheme::Proportional,
+        RewardScheme::Pplns { window },
+        RewardScheme::Score { half_life_seconds },
+    ];
+
+    for scheme in schemes.iter() {
+        scheme.validate()?;
+    }
+
+    let report = simulate(pool, schemes, from_block, to_block)
+        .await
+        .map_err(|e| format!("Failed to simulate payouts: {}", e))?;
+
+    for datum_round in report.skipped.iter() {
+        println!("block {} datum {}: skipped, no round to replay", datum_round.block_number, datum_round.transaction_hash);
+    }
+
+    println!("Simulated {} datums, skipped {} with no round to replay.", report.datums, report.skipped.len());
+
+    for simulation in report.schemes.iter() {
+        let scheme = match simulation.scheme {
+            RewardScheme::Proportional => String::from("proportional"),
+            RewardScheme::Pplns { window } => format!("pplns (window {} shares)", window),
+            RewardScheme::Score { half_life_seconds } => format!("score (half life {}s)", half_life_seconds),
+        };
+
+        println!();
+        println!("{}, pool fee {}", scheme, simulation.pool_fee);
+
+        for miner in simulation.miners.iter() {
+            println!(
+                "miner {} ({}): total {} mean {:.0} variance {:.0} std dev {:.0}",
+                miner.miner_id,
+                miner.address,
+                miner.total(),
+                miner.mean(),
+                miner.variance(),
+                miner.variance().sqrt()
+            );
+        }
+    }
+
+    Ok(())
+}
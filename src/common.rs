@@ -1,5 +1,8 @@
 use serde::{Serialize, Deserialize};
 
+/// The server's database pool size, shared with `/metrics` since sqlx does not report it back.
+pub const DATABASE_MAX_CONNECTIONS: u32 = 10;
+
 #[derive(Debug,Serialize, Deserialize)]
 pub struct GenericMessageResponse {
     pub message: String
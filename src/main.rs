@@ -6,10 +6,17 @@ use std::sync::Arc;
 use actix_web::web::Data;
 use actix_web::{get, App, HttpResponse, HttpServer, Responder};
 use service::block::{BlockService, block_updater};
+use service::fee::FeePolicy;
+use service::reward::RewardScheme;
+use service::reconciliation::reconciliation_updater;
+use service::share_aggregate::share_aggregator;
 use service::submission::submission_updater;
+use service::webhook::{webhook_dispatcher, webhook_secret, webhook_urls};
+use service::withdrawal::withdrawal_watcher;
 use sqlx::postgres::PgPoolOptions;
 
 mod address;
+mod cli;
 mod model;
 mod service;
 mod routes;
@@ -27,6 +34,12 @@ async fn main() -> std::io::Result<()> {
     }
 
     env_logger::init();
+
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(command) = args.get(1) {
+        return run_command(command, &args[2..]).await;
+    }
+
     pool_is_configured();
 
     let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
@@ -35,15 +48,19 @@ async fn main() -> std::io::Result<()> {
     let listen_port: u16 = listen_port_str.parse().expect("Invalid port number");
 
     let pool = PgPoolOptions::new()
-        .max_connections(10)
+        .max_connections(common::DATABASE_MAX_CONNECTIONS)
         .connect(&database_url).await.unwrap();
 
     sqlx::migrate!().run(&pool).await.unwrap();
 
     let block_service = Arc::new(BlockService::new());
 
-    tokio::spawn(block_updater(block_service.clone()));
+    tokio::spawn(block_updater(block_service.clone(), pool.clone()));
     tokio::spawn(submission_updater(pool.clone()));
+    tokio::spawn(reconciliation_updater(pool.clone()));
+    tokio::spawn(withdrawal_watcher(pool.clone()));
+    tokio::spawn(share_aggregator(pool.clone()));
+    tokio::spawn(webhook_dispatcher(pool.clone()));
     
     let whitelist = parse_whitelist();
 
@@ -56,19 +73,60 @@ async fn main() -> std::io::Result<()> {
             .service(routes::work::work)
             .service(routes::submit::submit)
             .service(routes::hashrate::hashrate)
+            .service(routes::hashrate::history)
+            .service(routes::round::round)
+            .service(routes::balance::balance)
+            .service(routes::payout::preview)
+            .service(routes::pool::stats)
+            .service(routes::pool::luck)
+            .service(routes::block::blocks)
+            .service(routes::admin::discrepancies)
+            .service(routes::admin::fee_overrides)
+            .service(routes::admin::set_fee_override)
+            .service(routes::admin::delete_fee_override)
+            .service(routes::admin::ban_miner)
+            .service(routes::admin::unban_miner)
+            .service(routes::metrics::metrics)
+            .service(routes::miner::stats)
+            .service(routes::miner::history)
+            .service(routes::miner::payout_address)
+            .service(routes::miner::set_payout_address)
+            .service(routes::miner::payout_splits)
+            .service(routes::miner::set_payout_splits)
+            .service(routes::miner::solo_mode)
+            .service(routes::miner::set_solo_mode)
     })
     .bind((listen_address, listen_port))?
     .run()
     .await
 }
 
+async fn run_command(command: &str, args: &[String]) -> std::io::Result<()> {
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+    let pool = PgPoolOptions::new()
+        .max_connections(2)
+        .connect(&database_url).await.unwrap();
+
+    cli::run(&pool, command, args)
+        .await
+        .map_err(std::io::Error::other)
+}
+
 fn pool_is_configured() {
     std::env::var("POOL_CONTRACT_ADDRESS").expect("POOL_CONTRACT_ADDRESS must be set");
     std::env::var("POOL_SCRIPT_HASH").expect("POOL_CONTRACT_ADDRESS must be set");
     std::env::var("POOL_OUTPUT_REFERENCE").expect("POOL_CONTRACT_ADDRESS must be set");
-    std::env::var("POOL_FIXED_FEE").expect("POOL_FIXED_FEE must be set");
+    if std::env::var("POOL_FEE_POLICY").is_err() {
+        std::env::var("POOL_FIXED_FEE").expect("POOL_FEE_POLICY or POOL_FIXED_FEE must be set");
+    }
+    FeePolicy::from_env();
+    RewardScheme::from_env();
     std::env::var("KUPO_URL").expect("KUPO_URL must be set");
     std::env::var("OGMIOS_URL").expect("OGMIOS_URL must be set");
+    if !webhook_urls().is_empty() {
+        webhook_secret().expect("WEBHOOK_SECRET must be set when WEBHOOK_URLS is");
+    }
 }
 
 fn parse_whitelist() -> HashSet<String> {
@@ -0,0 +1,118 @@
+use sqlx::{Postgres, Pool};
+
+use crate::service::block::Block;
+
+pub struct StoredBlock {
+    pub block_number: i32,
+    pub current_hash: String,
+    pub leading_zeroes: i32,
+    pub difficulty_number: i32,
+    pub epoch_time: i64,
+    pub current_posix_time: i64,
+    pub transaction_id: String,
+    pub output_index: i64,
+}
+
+impl From<StoredBlock> for Block {
+    fn from(stored: StoredBlock) -> Self {
+        Block {
+            block_number: stored.block_number,
+            current_hash: hex::decode(&stored.current_hash).unwrap_or_default(),
+            leading_zeroes: stored.leading_zeroes as u8,
+            difficulty_number: stored.difficulty_number as u16,
+            epoch_time: stored.epoch_time as u64,
+            current_time: stored.current_posix_time as u64,
+            transaction_id: stored.transaction_id,
+            output_index: stored.output_index,
+            ..Default::default()
+        }
+    }
+}
+
+pub async fn create(pool: &Pool<Postgres>, block: &Block) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO blocks
+        (block_number, current_hash, leading_zeroes, difficulty_number, epoch_time,
+         current_posix_time, transaction_id, output_index, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+        ON CONFLICT (block_number) DO NOTHING
+        "#,
+        block.block_number,
+        hex::encode(&block.current_hash),
+        block.leading_zeroes as i32,
+        block.difficulty_number as i32,
+        block.epoch_time as i64,
+        block.current_time as i64,
+        block.transaction_id,
+        block.output_index
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+pub async fn get_by_block_range(
+    pool: &Pool<Postgres>,
+    from_block: i32,
+    to_block: i32,
+) -> Result<Vec<StoredBlock>, sqlx::Error> {
+    sqlx::query_as!(
+        StoredBlock,
+        r#"
+        SELECT block_number, current_hash, leading_zeroes, difficulty_number, epoch_time,
+               current_posix_time, transaction_id, output_index
+        FROM blocks
+        WHERE block_number BETWEEN $1 AND $2
+        ORDER BY block_number ASC
+        "#,
+        from_block, to_block
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_latest(pool: &Pool<Postgres>, limit: i64) -> Result<Vec<StoredBlock>, sqlx::Error> {
+    sqlx::query_as!(
+        StoredBlock,
+        r#"
+        SELECT block_number, current_hash, leading_zeroes, difficulty_number, epoch_time,
+               current_posix_time, transaction_id, output_index
+        FROM blocks
+        ORDER BY block_number DESC
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_by_block_number(pool: &Pool<Postgres>, block_number: i32) -> Result<Option<StoredBlock>, sqlx::Error> {
+    sqlx::query_as!(
+        StoredBlock,
+        r#"
+        SELECT block_number, current_hash, leading_zeroes, difficulty_number, epoch_time,
+               current_posix_time, transaction_id, output_index
+        FROM blocks
+        WHERE block_number = $1
+        "#,
+        block_number
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+// The highest block recorded. Blocks can be missing below it, but never above the chain's actual height.
+pub async fn get_height(pool: &Pool<Postgres>) -> Result<Option<i32>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT MAX(block_number) as height
+        FROM blocks
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.height)
+}
@@ -1,6 +1,31 @@
+use serde::Serialize;
 use sqlx::{Postgres, Pool};
 use chrono::NaiveDateTime;
 
+/// What became of a datum the pool submitted, as reported by every endpoint that lists found blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatumStatus {
+    Pending,
+    Confirmed,
+    /// Never made it on chain, and no other block took its place.
+    Rejected,
+    /// Never made it on chain because another miner's block got there first.
+    Orphaned,
+}
+
+impl DatumStatus {
+    /// `orphaned` is whether another block is on chain where the datum's would have been.
+    pub fn new(rejected: bool, orphaned: bool, confirmed: bool) -> Self {
+        match (rejected, orphaned, confirmed) {
+            (true, true, _) => DatumStatus::Orphaned,
+            (true, false, _) => DatumStatus::Rejected,
+            (false, _, true) => DatumStatus::Confirmed,
+            (false, _, false) => DatumStatus::Pending,
+        }
+    }
+}
+
 pub struct DatumSubmission {
     pub transaction_hash: String,
     pub sha: String,
@@ -16,14 +41,16 @@ pub async fn create(
     transaction_hash: String,
     sha: String,
     block_number: i32,
+    estimated_hashes: f64,
+    expected_hashes: f64,
 ) -> Result<u64, sqlx::Error> {
     sqlx::query!(
         r#"
         INSERT INTO datum_submissions
-        (transaction_hash, sha, block_number, created_at, rejected)
-        VALUES ($1, $2, $3, NOW(), FALSE)
+        (transaction_hash, sha, block_number, created_at, rejected, estimated_hashes, expected_hashes)
+        VALUES ($1, $2, $3, NOW(), FALSE, $4, $5)
         "#,
-        transaction_hash, sha, block_number
+        transaction_hash, sha, block_number, estimated_hashes, expected_hashes
     )
     .execute(pool)
     .await
@@ -49,6 +76,17 @@ pub async fn accept(pool: &Pool<Postgres>, submissions: Vec<DatumSubmission>) ->
         )
         .execute(&mut tx)
         .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE payouts
+            SET status = 'confirmed', updated_at = NOW()
+            WHERE transaction_hash = $1
+            "#,
+            submission.transaction_hash
+        )
+        .execute(&mut tx)
+        .await?;
     }
 
     tx.commit().await?;
@@ -60,10 +98,6 @@ pub async fn reject(pool: &Pool<Postgres>, submissions: Vec<DatumSubmission>) ->
     let mut tx = pool.begin().await?;
 
     for submission in submissions.iter() {
-        if submission.confirmed_in_slot.is_none() {
-            continue;
-        };
-
         sqlx::query!(
             r#"
             UPDATE datum_submissions
@@ -74,6 +108,17 @@ pub async fn reject(pool: &Pool<Postgres>, submissions: Vec<DatumSubmission>) ->
         )
         .execute(&mut tx)
         .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE payouts
+            SET status = 'rejected', updated_at = NOW()
+            WHERE transaction_hash = $1
+            "#,
+            submission.transaction_hash
+        )
+        .execute(&mut tx)
+        .await?;
     }
 
     tx.commit().await?;
@@ -96,19 +141,234 @@ pub async fn get_unconfirmed(pool: &Pool<Postgres>) -> Result<Vec<DatumSubmissio
     .await
 }
 
-pub async fn get_newest_confirmed_datum(pool: &Pool<Postgres>) -> Result<Option<DatumSubmission>, sqlx::Error> {
+pub async fn get_by_sha(pool: &Pool<Postgres>, sha: &str) -> Result<Option<DatumSubmission>, sqlx::Error> {
     sqlx::query_as!(
         DatumSubmission,
         r#"
-        SELECT ds.transaction_hash, ds.sha, ds.confirmed_in_slot,
-               ds.rejected, ds.created_at, ds.confirmed_at, pow.block_number
-        FROM datum_submissions AS ds
-        JOIN proof_of_work AS pow ON ds.sha = pow.sha
-        WHERE confirmed_in_slot IS NOT NULL
-        ORDER BY confirmed_in_slot DESC
-        LIMIT 1
+        SELECT transaction_hash, sha, created_at, rejected, block_number,
+               confirmed_in_slot, confirmed_at
+        FROM datum_submissions
+        WHERE sha = $1
         "#,
+        sha
     )
     .fetch_optional(pool)
     .await
 }
+
+// Confirmed datums whose payouts were recorded but have not yet been checked against the bank on chain.
+pub async fn get_unreconciled(pool: &Pool<Postgres>) -> Result<Vec<DatumSubmission>, sqlx::Error> {
+    sqlx::query_as!(
+        DatumSubmission,
+        r#"
+        SELECT transaction_hash, sha, created_at, rejected, block_number,
+               confirmed_in_slot, confirmed_at
+        FROM datum_submissions AS ds
+        WHERE confirmed_in_slot IS NOT NULL AND rejected = false AND reconciled_at IS NULL
+          AND EXISTS (SELECT 1 FROM payouts WHERE payouts.transaction_hash = ds.transaction_hash)
+        ORDER BY confirmed_in_slot
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// A pooled datum together with the round it paid out and the miner whose share found its block.
+#[derive(Debug)]
+pub struct DatumRound {
+    pub transaction_hash: String,
+    pub block_number: i32,
+    pub round_id: Option<i32>,
+    pub ended_at: Option<NaiveDateTime>,
+    pub finder_id: i32,
+    pub finder_address: String,
+}
+
+pub async fn get_rounds_by_block_range(
+    pool: &Pool<Postgres>,
+    from_block: i32,
+    to_block: i32,
+) -> Result<Vec<DatumRound>, sqlx::Error> {
+    sqlx::query_as!(
+        DatumRound,
+        r#"
+        SELECT ds.transaction_hash, ds.block_number,
+               rounds.id as "round_id?", rounds.ended_at as "ended_at?",
+               miners.id as finder_id, miners.address as finder_address
+        FROM datum_submissions AS ds
+        JOIN proof_of_work AS pow ON pow.sha = ds.sha AND pow.block_number = ds.block_number
+        JOIN miners ON miners.id = pow.miner_id
+        LEFT JOIN rounds ON rounds.transaction_hash = ds.transaction_hash
+        WHERE ds.rejected = false AND ds.block_number BETWEEN $1 AND $2
+        ORDER BY ds.block_number
+        "#,
+        from_block, to_block
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// Blocks the miner found for the pool or for themselves, not counting datums that never made it on chain.
+pub async fn count_found_by_miner(pool: &Pool<Postgres>, miner_id: i32) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "blocks_found!"
+        FROM datum_submissions AS ds
+        JOIN proof_of_work AS pow ON pow.sha = ds.sha AND pow.block_number = ds.block_number
+        WHERE pow.miner_id = $1 AND ds.rejected = false
+        "#,
+        miner_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.blocks_found)
+}
+
+pub struct DatumCounts {
+    pub found: i64,
+    pub pending: i64,
+    pub confirmed: i64,
+    pub rejected: i64,
+    pub orphaned: i64,
+    pub last_confirmed_at: Option<NaiveDateTime>,
+}
+
+// Orphaned is worked out as in get_found_blocks, so the counts agree with what /blocks lists.
+pub async fn count_by_status(pool: &Pool<Postgres>) -> Result<DatumCounts, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT ds.rejected,
+               EXISTS (
+                   SELECT 1 FROM blocks
+                   WHERE blocks.block_number = ds.block_number + 1 AND blocks.transaction_id <> ds.transaction_hash
+               ) as "orphaned!",
+               ds.confirmed_in_slot IS NOT NULL as "confirmed!",
+               COUNT(*) as "count!",
+               MAX(ds.confirmed_at) as last_confirmed_at
+        FROM datum_submissions AS ds
+        GROUP BY 1, 2, 3
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut counts = DatumCounts { found: 0, pending: 0, confirmed: 0, rejected: 0, orphaned: 0, last_confirmed_at: None };
+    for row in rows {
+        counts.found += row.count;
+
+        match DatumStatus::new(row.rejected, row.orphaned, row.confirmed) {
+            DatumStatus::Pending => counts.pending += row.count,
+            DatumStatus::Confirmed => {
+                counts.confirmed += row.count;
+                counts.last_confirmed_at = counts.last_confirmed_at.max(row.last_confirmed_at);
+            }
+            DatumStatus::Rejected => counts.rejected += row.count,
+            DatumStatus::Orphaned => counts.orphaned += row.count,
+        }
+    }
+
+    Ok(counts)
+}
+
+pub async fn get_last_created_at(pool: &Pool<Postgres>) -> Result<Option<NaiveDateTime>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT MAX(created_at) as last_created_at
+        FROM datum_submissions
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.last_created_at)
+}
+
+/// A datum with the effort that went into finding its block.
+pub struct DatumEffort {
+    pub transaction_hash: String,
+    pub block_number: i32,
+    pub created_at: NaiveDateTime,
+    pub rejected: bool,
+    pub orphaned: bool,
+    pub confirmed_in_slot: Option<i32>,
+    pub estimated_hashes: f64,
+    pub expected_hashes: f64,
+}
+
+// The latest datums first, skipping those submitted before effort was tracked.
+pub async fn get_efforts(pool: &Pool<Postgres>, limit: i64) -> Result<Vec<DatumEffort>, sqlx::Error> {
+    sqlx::query_as!(
+        DatumEffort,
+        r#"
+        SELECT transaction_hash, block_number, created_at, rejected, confirmed_in_slot,
+               EXISTS (
+                   SELECT 1 FROM blocks
+                   WHERE blocks.block_number = ds.block_number + 1 AND blocks.transaction_id <> ds.transaction_hash
+               ) as "orphaned!",
+               estimated_hashes as "estimated_hashes!", expected_hashes as "expected_hashes!"
+        FROM datum_submissions AS ds
+        WHERE estimated_hashes IS NOT NULL AND expected_hashes IS NOT NULL
+        ORDER BY created_at DESC
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// A datum the pool submitted, as listed by `GET /blocks`.
+pub struct FoundBlock {
+    pub block_number: i32,
+    pub transaction_hash: String,
+    pub sha: String,
+    pub finder_address: String,
+    pub created_at: NaiveDateTime,
+    pub rejected: bool,
+    /// Another miner's block is on chain where this one would have been.
+    pub orphaned: bool,
+    pub confirmed_in_slot: Option<i32>,
+    pub confirmed_at: Option<NaiveDateTime>,
+    pub total_payout: i64,
+}
+
+// The datum's block number is the block it was mined on, so the block it would have made is the next one.
+pub async fn get_found_blocks(pool: &Pool<Postgres>, limit: i64, offset: i64) -> Result<Vec<FoundBlock>, sqlx::Error> {
+    sqlx::query_as!(
+        FoundBlock,
+        r#"
+        SELECT ds.block_number, ds.transaction_hash, ds.sha, miners.address as finder_address,
+               ds.created_at, ds.rejected, ds.confirmed_in_slot, ds.confirmed_at,
+               EXISTS (
+                   SELECT 1 FROM blocks
+                   WHERE blocks.block_number = ds.block_number + 1 AND blocks.transaction_id <> ds.transaction_hash
+               ) as "orphaned!",
+               COALESCE(
+                   (SELECT SUM(amount) FROM payouts WHERE payouts.transaction_hash = ds.transaction_hash), 0
+               )::BIGINT as "total_payout!"
+        FROM datum_submissions AS ds
+        JOIN proof_of_work AS pow ON pow.sha = ds.sha AND pow.block_number = ds.block_number
+        JOIN miners ON miners.id = pow.miner_id
+        ORDER BY ds.created_at DESC
+        LIMIT $1 OFFSET $2
+        "#,
+        limit, offset
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_unconfirmed(pool: &Pool<Postgres>) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "unconfirmed!"
+        FROM datum_submissions
+        WHERE confirmed_in_slot IS NULL AND rejected = false
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.unconfirmed)
+}
@@ -0,0 +1,60 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{Postgres, Pool};
+
+#[derive(Debug, Serialize)]
+pub struct FeeOverride {
+    pub miner_id: i32,
+    pub policy: String,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+pub async fn get_fee_overrides(pool: &Pool<Postgres>) -> Result<Vec<FeeOverride>, sqlx::Error> {
+    sqlx::query_as!(
+        FeeOverride,
+        r#"
+        SELECT miner_id, policy, note, created_at, updated_at
+        FROM miner_fee_overrides
+        ORDER BY miner_id
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn upsert(
+    pool: &Pool<Postgres>,
+    miner_id: i32,
+    policy: &str,
+    note: Option<&str>,
+) -> Result<FeeOverride, sqlx::Error> {
+    sqlx::query_as!(
+        FeeOverride,
+        r#"
+        INSERT INTO miner_fee_overrides (miner_id, policy, note, created_at, updated_at)
+        VALUES ($1, $2, $3, NOW(), NOW())
+        ON CONFLICT (miner_id) DO UPDATE SET policy = $2, note = $3, updated_at = NOW()
+        RETURNING miner_id, policy, note, created_at, updated_at
+        "#,
+        miner_id,
+        policy,
+        note
+    )
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete(pool: &Pool<Postgres>, miner_id: i32) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM miner_fee_overrides
+        WHERE miner_id = $1
+        "#,
+        miner_id
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
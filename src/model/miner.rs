@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use sqlx::{Postgres, Pool};
 
 // Define the Miner structure to map with the database table.
@@ -6,6 +8,9 @@ pub struct Miner {
     pub address: String,
     pub pkh: String,
     pub sampling_difficulty: i32,
+    pub solo: bool,
+    pub payout_address: Option<String>,
+    pub banned: bool,
 }
 
 // Function to create a new miner.
@@ -18,7 +23,7 @@ pub async fn create_miner(pool: &Pool<Postgres>, pkh: String, address: String) -
         INSERT INTO miners
         (pkh, address)
         VALUES ($1, $2)
-        RETURNING id, address, pkh, sampling_difficulty
+        RETURNING id, address, pkh, sampling_difficulty, solo, payout_address, banned
         "#,
         pkh, address
     )
@@ -35,7 +40,7 @@ pub async fn get_miner_by_pkh(pool: &Pool<Postgres>, pkh: &str) -> Result<Option
     sqlx::query_as!(
         Miner,
         r#"
-        SELECT id, address, pkh, sampling_difficulty
+        SELECT id, address, pkh, sampling_difficulty, solo, payout_address, banned
         FROM miners
         WHERE pkh = $1
         "#,
@@ -45,6 +50,20 @@ pub async fn get_miner_by_pkh(pool: &Pool<Postgres>, pkh: &str) -> Result<Option
     .await
 }
 
+pub async fn get_miner_by_id(pool: &Pool<Postgres>, id: i32) -> Result<Option<Miner>, sqlx::Error> {
+    sqlx::query_as!(
+        Miner,
+        r#"
+        SELECT id, address, pkh, sampling_difficulty, solo, payout_address, banned
+        FROM miners
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
 pub async fn update_sampling_difficulty_by_pkh(pool: &Pool<Postgres>, pkh: &str, new_difficulty: u8) -> Result<Miner, sqlx::Error> {
     let result = sqlx::query_as!(
         Miner,
@@ -52,7 +71,7 @@ pub async fn update_sampling_difficulty_by_pkh(pool: &Pool<Postgres>, pkh: &str,
         UPDATE miners
         SET sampling_difficulty = $1
         WHERE pkh = $2
-        RETURNING id, address, pkh, sampling_difficulty
+        RETURNING id, address, pkh, sampling_difficulty, solo, payout_address, banned
         "#,
         new_difficulty as i32, 
         pkh
@@ -61,4 +80,41 @@ pub async fn update_sampling_difficulty_by_pkh(pool: &Pool<Postgres>, pkh: &str,
     .await?;
 
     Ok(result)
-}
\ No newline at end of file
+}
+
+pub async fn set_banned(pool: &Pool<Postgres>, id: i32, banned: bool) -> Result<Option<Miner>, sqlx::Error> {
+    sqlx::query_as!(
+        Miner,
+        r#"
+        UPDATE miners
+        SET banned = $1
+        WHERE id = $2
+        RETURNING id, address, pkh, sampling_difficulty, solo, payout_address, banned
+        "#,
+        banned,
+        id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+impl Miner {
+    /// Where the miner's rewards are credited.
+    pub fn payout_address(&self) -> &str {
+        self.payout_address.as_deref().unwrap_or(&self.address)
+    }
+}
+
+pub async fn get_payout_addresses(pool: &Pool<Postgres>) -> Result<HashMap<i32, String>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, payout_address as "payout_address!"
+        FROM miners
+        WHERE payout_address IS NOT NULL
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|row| (row.id, row.payout_address)).collect())
+}
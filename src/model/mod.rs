@@ -1,3 +1,16 @@
 pub mod miner;
 pub mod proof_of_work;
-pub mod datum_submission;
\ No newline at end of file
+pub mod datum_submission;
+pub mod block;
+pub mod payout;
+pub mod round;
+pub mod reconciliation;
+pub mod watermark;
+pub mod withdrawal;
+pub mod fee_override;
+pub mod payout_address;
+pub mod payout_split;
+pub mod share_rejection;
+pub mod share_aggregate;
+pub mod webhook_delivery;
+pub mod solo_mode;
@@ -0,0 +1,154 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{Postgres, Pool};
+
+pub struct NewPayout {
+    pub miner_id: i32,
+    pub address: String,
+    pub pkh: String,
+    pub share_count: i64,
+    pub estimated_hashes: f64,
+    pub amount: i64,
+    pub pool_fee: i64,
+    pub finders_fee: i64,
+    pub fee_policy: String,
+    pub fee_override: bool,
+    pub split_basis_points: Option<i32>,
+    pub solo: bool,
+}
+
+pub async fn create(
+    pool: &Pool<Postgres>,
+    transaction_hash: &str,
+    new_payouts: &[NewPayout],
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    for new_payout in new_payouts.iter() {
+        sqlx::query!(
+            r#"
+            INSERT INTO payouts
+            (transaction_hash, miner_id, address, pkh, share_count, estimated_hashes,
+             amount, pool_fee, finders_fee, fee_policy, fee_override, split_basis_points, solo,
+             status, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, 'pending', NOW(), NOW())
+            "#,
+            transaction_hash,
+            new_payout.miner_id,
+            new_payout.address,
+            new_payout.pkh,
+            new_payout.share_count,
+            new_payout.estimated_hashes,
+            new_payout.amount,
+            new_payout.pool_fee,
+            new_payout.finders_fee,
+            new_payout.fee_policy,
+            new_payout.fee_override,
+            new_payout.split_basis_points,
+            new_payout.solo
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(new_payouts.len() as u64)
+}
+
+// What was paid to the payment key hash in datums that are not yet confirmed, whoever earned it.
+pub async fn sum_pending_by_pkh(pool: &Pool<Postgres>, pkh: &str) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COALESCE(SUM(amount), 0)::BIGINT as "pending!"
+        FROM payouts
+        WHERE pkh = $1 AND status = 'pending'
+        "#,
+        pkh
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.pending)
+}
+
+pub struct PkhAmount {
+    pub pkh: String,
+    pub amount: i64,
+}
+
+// What a datum paid into the bank for each payment key hash.
+pub async fn sum_by_transaction(pool: &Pool<Postgres>, transaction_hash: &str) -> Result<Vec<PkhAmount>, sqlx::Error> {
+    sqlx::query_as!(
+        PkhAmount,
+        r#"
+        SELECT pkh, SUM(amount)::BIGINT as "amount!"
+        FROM payouts
+        WHERE transaction_hash = $1
+        GROUP BY pkh
+        "#,
+        transaction_hash
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[derive(Debug, Serialize)]
+pub struct Payout {
+    pub id: i32,
+    pub transaction_hash: String,
+    pub miner_id: i32,
+    pub address: String,
+    pub pkh: String,
+    pub share_count: i64,
+    pub estimated_hashes: f64,
+    pub amount: i64,
+    pub pool_fee: i64,
+    pub finders_fee: i64,
+    pub fee_policy: Option<String>,
+    pub fee_override: bool,
+    pub split_basis_points: Option<i32>,
+    pub solo: bool,
+    pub status: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+pub async fn get_by_miner(pool: &Pool<Postgres>, miner_id: i32, limit: i64) -> Result<Vec<Payout>, sqlx::Error> {
+    sqlx::query_as!(
+        Payout,
+        r#"
+        SELECT id, transaction_hash, miner_id, address, pkh, share_count, estimated_hashes,
+               amount, pool_fee, finders_fee, fee_policy, fee_override, split_basis_points, solo,
+               status, created_at, updated_at
+        FROM payouts
+        WHERE miner_id = $1
+        ORDER BY id DESC
+        LIMIT $2
+        "#,
+        miner_id,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// Payouts paid to the payment key hash, e.g. as someone's payout address or split payee.
+pub async fn get_by_pkh(pool: &Pool<Postgres>, pkh: &str, limit: i64) -> Result<Vec<Payout>, sqlx::Error> {
+    sqlx::query_as!(
+        Payout,
+        r#"
+        SELECT id, transaction_hash, miner_id, address, pkh, share_count, estimated_hashes,
+               amount, pool_fee, finders_fee, fee_policy, fee_override, split_basis_points, solo,
+               status, created_at, updated_at
+        FROM payouts
+        WHERE pkh = $1
+        ORDER BY id DESC
+        LIMIT $2
+        "#,
+        pkh,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,91 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{Postgres, Pool};
+
+#[derive(Debug, Serialize)]
+pub struct PayoutAddressChange {
+    pub id: i32,
+    pub miner_id: i32,
+    pub change_number: i32,
+    pub previous_address: Option<String>,
+    pub payout_address: String,
+    pub signature: String,
+    pub key: String,
+    pub created_at: NaiveDateTime,
+}
+
+pub async fn get_changes(pool: &Pool<Postgres>, miner_id: i32) -> Result<Vec<PayoutAddressChange>, sqlx::Error> {
+    sqlx::query_as!(
+        PayoutAddressChange,
+        r#"
+        SELECT id, miner_id, change_number, previous_address, payout_address, signature, key, created_at
+        FROM payout_address_changes
+        WHERE miner_id = $1
+        ORDER BY change_number DESC
+        "#,
+        miner_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_changes(pool: &Pool<Postgres>, miner_id: i32) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM payout_address_changes
+        WHERE miner_id = $1
+        "#,
+        miner_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.count)
+}
+
+// Records the change and applies it together. A change number that was already used means the
+// signature was for a change someone else made first, so it fails on the unique constraint.
+pub async fn change(
+    pool: &Pool<Postgres>,
+    miner_id: i32,
+    change_number: i32,
+    previous_address: Option<&str>,
+    payout_address: &str,
+    signature: &str,
+    key: &str,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO payout_address_changes
+        (miner_id, change_number, previous_address, payout_address, signature, key, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, NOW())
+        "#,
+        miner_id,
+        change_number,
+        previous_address,
+        payout_address,
+        signature,
+        key
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE miners
+        SET payout_address = $1
+        WHERE id = $2
+        "#,
+        payout_address,
+        miner_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
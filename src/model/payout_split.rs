@@ -0,0 +1,136 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{Postgres, Pool};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayoutSplit {
+    pub miner_id: i32,
+    pub address: String,
+    pub basis_points: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PayoutSplitChange {
+    pub id: i32,
+    pub miner_id: i32,
+    pub change_number: i32,
+    pub splits: String,
+    pub signature: String,
+    pub key: String,
+    pub created_at: NaiveDateTime,
+}
+
+pub async fn get_all(pool: &Pool<Postgres>) -> Result<Vec<PayoutSplit>, sqlx::Error> {
+    sqlx::query_as!(
+        PayoutSplit,
+        r#"
+        SELECT miner_id, address, basis_points
+        FROM payout_splits
+        ORDER BY miner_id, address
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_by_miner(pool: &Pool<Postgres>, miner_id: i32) -> Result<Vec<PayoutSplit>, sqlx::Error> {
+    sqlx::query_as!(
+        PayoutSplit,
+        r#"
+        SELECT miner_id, address, basis_points
+        FROM payout_splits
+        WHERE miner_id = $1
+        ORDER BY address
+        "#,
+        miner_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_changes(pool: &Pool<Postgres>, miner_id: i32) -> Result<Vec<PayoutSplitChange>, sqlx::Error> {
+    sqlx::query_as!(
+        PayoutSplitChange,
+        r#"
+        SELECT id, miner_id, change_number, splits, signature, key, created_at
+        FROM payout_split_changes
+        WHERE miner_id = $1
+        ORDER BY change_number DESC
+        "#,
+        miner_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_changes(pool: &Pool<Postgres>, miner_id: i32) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM payout_split_changes
+        WHERE miner_id = $1
+        "#,
+        miner_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.count)
+}
+
+// Replaces the miner's splits and records the change together. As with payout addresses, a change
+// number that was already used fails on the unique constraint.
+pub async fn replace(
+    pool: &Pool<Postgres>,
+    miner_id: i32,
+    change_number: i32,
+    splits: &[PayoutSplit],
+    signed_splits: &str,
+    signature: &str,
+    key: &str,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO payout_split_changes
+        (miner_id, change_number, splits, signature, key, created_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        "#,
+        miner_id,
+        change_number,
+        signed_splits,
+        signature,
+        key
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM payout_splits
+        WHERE miner_id = $1
+        "#,
+        miner_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    for split in splits.iter() {
+        sqlx::query!(
+            r#"
+            INSERT INTO payout_splits (miner_id, address, basis_points)
+            VALUES ($1, $2, $3)
+            "#,
+            miner_id,
+            split.address,
+            split.basis_points
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
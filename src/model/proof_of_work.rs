@@ -3,13 +3,16 @@ use sqlx::{Postgres, Pool};
 
 use crate::{service::proof_of_work::ProcessedSubmissionEntry};
 
-#[derive(Clone)]
+use super::share_aggregate::{get_aggregated_range, get_aggregated_until};
+
+#[derive(Debug, Clone)]
 pub struct ProofOfWork {
     pub miner_id: i32,
     pub miner_address: String,
     pub block_number: i32,
     pub sha: String,
     pub nonce: String,
+    pub sampling_difficulty: i32,
     pub created_at: NaiveDateTime,
 }
 
@@ -18,6 +21,8 @@ pub async fn create(
     miner_id: i32,
     block_number: i32,
     new_pows: &Vec<ProcessedSubmissionEntry>,
+    solo: bool,
+    worker: Option<&str>,
 ) -> Result<u64, sqlx::Error> {
     let mut tx = pool.begin().await?;
     let mut success_count = 0;
@@ -29,14 +34,15 @@ pub async fn create(
         match sqlx::query!(
             r#"
             INSERT INTO proof_of_work
-            (miner_id, block_number, sha, nonce, sampling_difficulty, created_at)
-            VALUES ($1, $2, $3, $4, $5, NOW())
+            (miner_id, block_number, sha, nonce, sampling_difficulty, created_at, solo, round_id, worker)
+            VALUES ($1, $2, $3, $4, $5, NOW(), $6, CASE WHEN $6 THEN NULL ELSE (SELECT id FROM rounds WHERE status = 'open') END, $7)
+            ON CONFLICT (sha, block_number) DO NOTHING
             "#,
-            miner_id, block_number, hex_sha, hex_nonce, new_pow.sampling_difficulty as i32
+            miner_id, block_number, hex_sha, hex_nonce, new_pow.sampling_difficulty as i32, solo, worker
         )
         .execute(&mut tx)
         .await {
-            Ok(_) => success_count += 1,
+            Ok(result) => success_count += result.rows_affected(),
             Err(e) => {
                 log::warn!("Rejected a sha due to {:?}", e);
             }
@@ -48,18 +54,26 @@ pub async fn create(
     Ok(success_count) // Returns the number of successful insertions.
 }
 
-pub async fn get_oldest(pool: &Pool<Postgres>) -> Result<Option<ProofOfWork>, sqlx::Error> {
+pub async fn get_by_block_range(
+    pool: &Pool<Postgres>,
+    miner_id: Option<i32>,
+    from_block: i32,
+    to_block: i32,
+) -> Result<Vec<ProofOfWork>, sqlx::Error> {
     sqlx::query_as!(
         ProofOfWork,
         r#"
-        SELECT miner_id, miners.address as miner_address, block_number, sha, nonce, created_at
+        SELECT miner_id, miners.address as miner_address, block_number, sha, nonce,
+               proof_of_work.sampling_difficulty, created_at
         FROM proof_of_work
         JOIN miners on miner_id = miners.id
-        ORDER BY created_at ASC
-        LIMIT 1
+        WHERE block_number BETWEEN $1 AND $2
+        AND ($3::INTEGER IS NULL OR miner_id = $3)
+        ORDER BY block_number ASC, created_at ASC
         "#,
+        from_block, to_block, miner_id
     )
-    .fetch_optional(pool)
+    .fetch_all(pool)
     .await
 }
 
@@ -78,8 +92,13 @@ pub async fn cleanup_old_proofs(pool: &Pool<Postgres>, num_to_retain: i64) -> Re
     .fetch_optional(pool)
     .await?;
 
+    // Shares are only deleted once they have been rolled into the aggregates.
+    let Some(aggregated_until) = get_aggregated_until(pool).await? else {
+        return Ok(());
+    };
+
     if let Some(row) = date_of_nth_oldest_confirmed_datum_result {
-        let oldest_datum_date = row.confirmed_at;
+        let oldest_datum_date = row.confirmed_at.map(|confirmed_at| confirmed_at.min(aggregated_until));
         
         sqlx::query!(
             r#"
@@ -89,6 +108,21 @@ pub async fn cleanup_old_proofs(pool: &Pool<Postgres>, num_to_retain: i64) -> Re
                 SELECT sha, block_number
                 FROM datum_submissions
             )
+            AND (round_id IS NULL OR round_id IN (
+                SELECT id
+                FROM rounds
+                WHERE status IN ('confirmed', 'rejected')
+            ))
+            "#,
+            oldest_datum_date
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM share_rejections
+            WHERE created_at < $1
             "#,
             oldest_datum_date
         )
@@ -107,42 +141,272 @@ pub struct MinerProofCount {
     pub sampling_difficulty: i32
 }
 
+// Shares in whole buckets that have been aggregated are counted from the aggregates, so counts outlive the proofs.
 pub async fn count_by_time_range(
     pool: &Pool<Postgres>,
     miner_id: Option<i32>,
     start_time: NaiveDateTime,
     end_time: NaiveDateTime,
 ) -> Result<Vec<MinerProofCount>, sqlx::Error> {
-    match miner_id {
-        Some(id) => {
-            sqlx::query_as!(
-                MinerProofCount,
-                r#"
-                SELECT miner_id, miners.address as miner_address, proof_of_work.sampling_difficulty, COUNT(*) as "proof_count!"
-                FROM proof_of_work
-                JOIN miners on miner_id = miners.id
-                WHERE miner_id = $1 AND created_at BETWEEN $2 AND $3
-                GROUP BY miner_id, miners.address, proof_of_work.sampling_difficulty
-                "#,
-                id, start_time, end_time
-            )
-            .fetch_all(pool)
-            .await
-        },
-        None => {
-            sqlx::query_as!(
-                MinerProofCount,
-                r#"
-                SELECT miner_id, miners.address as miner_address, proof_of_work.sampling_difficulty, COUNT(*) as "proof_count!"
-                FROM proof_of_work
-                JOIN miners on miner_id = miners.id
-                WHERE created_at BETWEEN $1 AND $2
-                GROUP BY miner_id, miners.address, proof_of_work.sampling_difficulty
-                "#,
-                start_time, end_time
-            )
-            .fetch_all(pool)
-            .await
-        },
-    }
-}
\ No newline at end of file
+    let (aggregated_from, aggregated_to) = get_aggregated_range(pool, start_time, end_time).await?;
+
+    sqlx::query_as!(
+        MinerProofCount,
+        r#"
+        SELECT counts.miner_id as "miner_id!", miners.address as miner_address,
+               counts.sampling_difficulty as "sampling_difficulty!", SUM(counts.proof_count)::BIGINT as "proof_count!"
+        FROM (
+            SELECT miner_id, sampling_difficulty, COUNT(*) as proof_count
+            FROM proof_of_work
+            WHERE ($1::INTEGER IS NULL OR miner_id = $1)
+              AND created_at BETWEEN $2 AND $3
+              AND NOT (created_at >= $4 AND created_at < $5)
+            GROUP BY 1, 2
+            UNION ALL
+            SELECT miner_id, sampling_difficulty, SUM(share_count) as proof_count
+            FROM share_aggregates
+            WHERE ($1::INTEGER IS NULL OR miner_id = $1)
+              AND bucket_start >= $4 AND bucket_start < $5
+            GROUP BY 1, 2
+        ) AS counts
+        JOIN miners on counts.miner_id = miners.id
+        GROUP BY counts.miner_id, miners.address, counts.sampling_difficulty
+        "#,
+        miner_id, start_time, end_time, aggregated_from, aggregated_to
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub struct BucketProofCount {
+    /// The start of the bucket, in seconds since the epoch.
+    pub bucket: i64,
+    pub proof_count: i64,
+    pub sampling_difficulty: i32,
+}
+
+// Proof counts in `interval_seconds` buckets aligned to the epoch, for one miner or worker, or the whole pool.
+// Shares that have been aggregated are counted from the aggregates, so history outlives the proofs.
+pub async fn count_by_interval(
+    pool: &Pool<Postgres>,
+    miner_id: Option<i32>,
+    worker: Option<&str>,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    interval_seconds: i64,
+) -> Result<Vec<BucketProofCount>, sqlx::Error> {
+    let aggregated_until = get_aggregated_until(pool).await?.unwrap_or_default();
+
+    sqlx::query_as!(
+        BucketProofCount,
+        r#"
+        SELECT bucket as "bucket!", sampling_difficulty as "sampling_difficulty!", SUM(proof_count)::BIGINT as "proof_count!"
+        FROM (
+            SELECT (FLOOR(EXTRACT(EPOCH FROM created_at) / $5) * $5)::BIGINT as bucket,
+                   sampling_difficulty, COUNT(*) as proof_count
+            FROM proof_of_work
+            WHERE ($1::INTEGER IS NULL OR miner_id = $1)
+              AND ($2::TEXT IS NULL OR worker = $2)
+              AND created_at BETWEEN $3 AND $4
+              AND created_at >= $6
+            GROUP BY 1, 2
+            UNION ALL
+            SELECT (FLOOR(EXTRACT(EPOCH FROM bucket_start) / $5) * $5)::BIGINT as bucket,
+                   sampling_difficulty, SUM(share_count) as proof_count
+            FROM share_aggregates
+            WHERE ($1::INTEGER IS NULL OR miner_id = $1)
+              AND ($2::TEXT IS NULL OR worker = $2)
+              AND bucket_start BETWEEN $3 AND $4
+              AND bucket_start < $6
+            GROUP BY 1, 2
+        ) AS counts
+        GROUP BY bucket, sampling_difficulty
+        ORDER BY bucket
+        "#,
+        miner_id, worker, start_time, end_time, interval_seconds as f64, aggregated_until
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub struct WorkerProofCount {
+    pub worker: Option<String>,
+    pub proof_count: i64,
+    pub sampling_difficulty: i32,
+    pub last_share_at: NaiveDateTime,
+}
+
+// Counted like count_by_time_range. For a worker seen only in the aggregates, the last share is placed at the
+// start of the last bucket it was in.
+pub async fn count_workers_by_time_range(
+    pool: &Pool<Postgres>,
+    miner_id: i32,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+) -> Result<Vec<WorkerProofCount>, sqlx::Error> {
+    let (aggregated_from, aggregated_to) = get_aggregated_range(pool, start_time, end_time).await?;
+
+    sqlx::query_as!(
+        WorkerProofCount,
+        r#"
+        SELECT NULLIF(worker, '') as worker, sampling_difficulty as "sampling_difficulty!",
+               SUM(proof_count)::BIGINT as "proof_count!", MAX(last_share_at) as "last_share_at!"
+        FROM (
+            SELECT COALESCE(worker, '') as worker, sampling_difficulty, COUNT(*) as proof_count,
+                   MAX(created_at) as last_share_at
+            FROM proof_of_work
+            WHERE miner_id = $1
+              AND created_at BETWEEN $2 AND $3
+              AND NOT (created_at >= $4 AND created_at < $5)
+            GROUP BY 1, 2
+            UNION ALL
+            SELECT worker, sampling_difficulty, SUM(share_count) as proof_count, MAX(bucket_start) as last_share_at
+            FROM share_aggregates
+            WHERE miner_id = $1
+              AND bucket_start >= $4 AND bucket_start < $5
+            GROUP BY 1, 2
+        ) AS counts
+        GROUP BY 1, 2
+        "#,
+        miner_id, start_time, end_time, aggregated_from, aggregated_to
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub struct ActiveCounts {
+    pub miners: i64,
+    pub workers: i64,
+}
+
+// Shares mined without naming a worker are counted as one worker per miner.
+pub async fn count_active(pool: &Pool<Postgres>, since: NaiveDateTime) -> Result<ActiveCounts, sqlx::Error> {
+    sqlx::query_as!(
+        ActiveCounts,
+        r#"
+        SELECT COUNT(DISTINCT miner_id) as "miners!",
+               COUNT(DISTINCT miner_id::TEXT || ':' || COALESCE(worker, '')) as "workers!"
+        FROM proof_of_work
+        WHERE created_at >= $1
+        "#,
+        since
+    )
+    .fetch_one(pool)
+    .await
+}
+
+// Falls back to the start of the miner's last aggregated bucket once their proofs have been cleaned up.
+pub async fn get_last_share_at(pool: &Pool<Postgres>, miner_id: i32) -> Result<Option<NaiveDateTime>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT GREATEST(
+            (SELECT MAX(created_at) FROM proof_of_work WHERE miner_id = $1),
+            (SELECT MAX(bucket_start) FROM share_aggregates WHERE miner_id = $1)
+        ) as last_share_at
+        "#,
+        miner_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.last_share_at)
+}
+
+pub async fn count_latest(
+    pool: &Pool<Postgres>,
+    num_proofs: i64,
+    end_time: NaiveDateTime,
+) -> Result<Vec<MinerProofCount>, sqlx::Error> {
+    sqlx::query_as!(
+        MinerProofCount,
+        r#"
+        SELECT latest.miner_id as "miner_id!", miners.address as miner_address,
+               latest.sampling_difficulty as "sampling_difficulty!", COUNT(*) as "proof_count!"
+        FROM (
+            SELECT miner_id, sampling_difficulty
+            FROM proof_of_work
+            WHERE created_at <= $2 AND solo = false
+            ORDER BY created_at DESC
+            LIMIT $1
+        ) AS latest
+        JOIN miners on latest.miner_id = miners.id
+        GROUP BY latest.miner_id, miners.address, latest.sampling_difficulty
+        "#,
+        num_proofs, end_time
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_solo_since(
+    pool: &Pool<Postgres>,
+    since: NaiveDateTime,
+) -> Result<Vec<MinerProofCount>, sqlx::Error> {
+    sqlx::query_as!(
+        MinerProofCount,
+        r#"
+        SELECT miner_id, miners.address as miner_address, proof_of_work.sampling_difficulty, COUNT(*) as "proof_count!"
+        FROM proof_of_work
+        JOIN miners on miner_id = miners.id
+        WHERE proof_of_work.solo = true AND created_at >= $1
+        GROUP BY miner_id, miners.address, proof_of_work.sampling_difficulty
+        "#,
+        since
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[derive(Debug)]
+pub struct MinerProofScore {
+    pub miner_id: i32,
+    pub miner_address: String,
+    pub proof_count: i64,
+    pub sampling_difficulty: i32,
+    pub decayed_count: f64,
+}
+
+// Each proof counts for 1 at end_time, halving for every half_life_seconds it is older than that.
+pub async fn score_by_round(
+    pool: &Pool<Postgres>,
+    round_id: i32,
+    end_time: NaiveDateTime,
+    half_life_seconds: f64,
+) -> Result<Vec<MinerProofScore>, sqlx::Error> {
+    sqlx::query_as!(
+        MinerProofScore,
+        r#"
+        SELECT miner_id, miners.address as miner_address, proof_of_work.sampling_difficulty, COUNT(*) as "proof_count!",
+               SUM(EXP(GREATEST(
+                   -LN(2) * GREATEST(EXTRACT(EPOCH FROM ($2 - created_at))::DOUBLE PRECISION, 0) / $3,
+                   -700
+               ))) as "decayed_count!"
+        FROM proof_of_work
+        JOIN miners on miner_id = miners.id
+        WHERE round_id = $1
+        GROUP BY miner_id, miners.address, proof_of_work.sampling_difficulty
+        "#,
+        round_id, end_time, half_life_seconds
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_by_round(
+    pool: &Pool<Postgres>,
+    round_id: i32,
+) -> Result<Vec<MinerProofCount>, sqlx::Error> {
+    sqlx::query_as!(
+        MinerProofCount,
+        r#"
+        SELECT miner_id, miners.address as miner_address, proof_of_work.sampling_difficulty, COUNT(*) as "proof_count!"
+        FROM proof_of_work
+        JOIN miners on miner_id = miners.id
+        WHERE round_id = $1
+        GROUP BY miner_id, miners.address, proof_of_work.sampling_difficulty
+        "#,
+        round_id
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,90 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{Postgres, Pool};
+
+#[derive(Debug, Serialize)]
+pub struct Discrepancy {
+    pub id: i32,
+    pub transaction_hash: String,
+    pub pkh: Option<String>,
+    pub expected: Option<i64>,
+    pub actual: Option<i64>,
+    pub reason: String,
+    pub created_at: NaiveDateTime,
+}
+
+pub struct NewDiscrepancy {
+    pub pkh: Option<String>,
+    pub expected: Option<i64>,
+    pub actual: Option<i64>,
+    pub reason: String,
+}
+
+// Records the outcome of reconciling a datum, so it is only ever checked once.
+pub async fn record(
+    pool: &Pool<Postgres>,
+    transaction_hash: &str,
+    discrepancies: &[NewDiscrepancy],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    for discrepancy in discrepancies.iter() {
+        sqlx::query!(
+            r#"
+            INSERT INTO reconciliation_discrepancies
+            (transaction_hash, pkh, expected, actual, reason, created_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            "#,
+            transaction_hash,
+            discrepancy.pkh,
+            discrepancy.expected,
+            discrepancy.actual,
+            discrepancy.reason
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE datum_submissions
+        SET reconciled_at = NOW()
+        WHERE transaction_hash = $1
+        "#,
+        transaction_hash
+    )
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn get_discrepancies(pool: &Pool<Postgres>, limit: i64) -> Result<Vec<Discrepancy>, sqlx::Error> {
+    sqlx::query_as!(
+        Discrepancy,
+        r#"
+        SELECT id, transaction_hash, pkh, expected, actual, reason, created_at
+        FROM reconciliation_discrepancies
+        ORDER BY id DESC
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_discrepancies(pool: &Pool<Postgres>) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM reconciliation_discrepancies
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.count)
+}
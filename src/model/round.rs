@@ -0,0 +1,214 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{Postgres, Pool};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Round {
+    pub id: i32,
+    pub status: String,
+    pub started_at: NaiveDateTime,
+    pub ended_at: Option<NaiveDateTime>,
+    pub transaction_hash: Option<String>,
+}
+
+/// A round cut for a block whose datum was never recorded as submitted.
+pub struct AbandonedRound {
+    pub id: i32,
+    pub sha: String,
+    pub block_number: i32,
+}
+
+// Cuts the round currently collecting shares for the block `sha` was found on, and opens the next one in
+// the same transaction, so every share lands in exactly one round. The cut round stays pending until its
+// datum is confirmed on chain, which closes it, or can no longer land, which hands its shares back.
+pub async fn cut_open(
+    pool: &Pool<Postgres>,
+    ended_at: NaiveDateTime,
+    sha: &str,
+    block_number: i32,
+) -> Result<Round, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO rounds (status, started_at)
+        VALUES ('open', $1)
+        ON CONFLICT (status) WHERE status = 'open' DO NOTHING
+        "#,
+        ended_at
+    )
+    .execute(&mut tx)
+    .await?;
+
+    let closed_round = sqlx::query_as!(
+        Round,
+        r#"
+        UPDATE rounds
+        SET status = 'pending', ended_at = $1, sha = $2, block_number = $3
+        WHERE status = 'open'
+        RETURNING id, status, started_at, ended_at, transaction_hash
+        "#,
+        ended_at, sha, block_number
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO rounds (status, started_at)
+        VALUES ('open', $1)
+        "#,
+        ended_at
+    )
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(closed_round)
+}
+
+// Read only, so it is safe behind a GET. Closing and rejecting rounds always leave one open.
+pub async fn get_open(pool: &Pool<Postgres>) -> Result<Option<Round>, sqlx::Error> {
+    sqlx::query_as!(
+        Round,
+        r#"
+        SELECT id, status, started_at, ended_at, transaction_hash
+        FROM rounds
+        WHERE status = 'open'
+        "#,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn set_transaction_hash(pool: &Pool<Postgres>, round_id: i32, transaction_hash: &str) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE rounds
+        SET transaction_hash = $1
+        WHERE id = $2
+        "#,
+        transaction_hash, round_id
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+pub async fn confirm(pool: &Pool<Postgres>, transaction_hash: &str) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE rounds
+        SET status = 'confirmed'
+        WHERE transaction_hash = $1
+        "#,
+        transaction_hash
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+// For a round whose datum made it on chain without its transaction being recorded. Its shares were paid
+// all the same, so it must not be handed back.
+pub async fn confirm_unrecorded(pool: &Pool<Postgres>, round_id: i32) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE rounds
+        SET status = 'confirmed'
+        WHERE id = $1 AND status = 'pending'
+        "#,
+        round_id
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+// The round's datum never made it on chain, so its shares are handed to the open round to be paid next time.
+pub async fn reject(pool: &Pool<Postgres>, round_id: i32) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO rounds (status, started_at)
+        VALUES ('open', NOW())
+        ON CONFLICT (status) WHERE status = 'open' DO NOTHING
+        "#,
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE proof_of_work
+        SET round_id = (SELECT id FROM rounds WHERE status = 'open')
+        WHERE round_id = $1
+        "#,
+        round_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE rounds
+        SET status = 'rejected'
+        WHERE id = $1
+        "#,
+        round_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn get_by_transaction_hash(pool: &Pool<Postgres>, transaction_hash: &str) -> Result<Option<Round>, sqlx::Error> {
+    sqlx::query_as!(
+        Round,
+        r#"
+        SELECT id, status, started_at, ended_at, transaction_hash
+        FROM rounds
+        WHERE transaction_hash = $1
+        "#,
+        transaction_hash
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+// Rounds cut by a submission that never got as far as recording its transaction, e.g. across a restart.
+// Rounds cut before their block was recorded cannot be resolved, so they are left pending.
+pub async fn get_abandoned(pool: &Pool<Postgres>, cut_before: NaiveDateTime) -> Result<Vec<AbandonedRound>, sqlx::Error> {
+    sqlx::query_as!(
+        AbandonedRound,
+        r#"
+        SELECT id, sha as "sha!", block_number as "block_number!"
+        FROM rounds
+        WHERE status = 'pending' AND transaction_hash IS NULL AND ended_at < $1
+          AND sha IS NOT NULL AND block_number IS NOT NULL
+        "#,
+        cut_before
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_by_share(pool: &Pool<Postgres>, sha: &str) -> Result<Option<Round>, sqlx::Error> {
+    sqlx::query_as!(
+        Round,
+        r#"
+        SELECT rounds.id, rounds.status, rounds.started_at, rounds.ended_at, rounds.transaction_hash
+        FROM proof_of_work
+        JOIN rounds ON proof_of_work.round_id = rounds.id
+        WHERE proof_of_work.sha = $1
+        "#,
+        sha
+    )
+    .fetch_optional(pool)
+    .await
+}
@@ -0,0 +1,121 @@
+use chrono::NaiveDateTime;
+use sqlx::{Postgres, Pool};
+
+use super::watermark;
+
+pub const SHARE_AGGREGATE_WATERMARK: &str = "share_aggregates";
+
+/// Shares are rolled up into buckets of this many seconds, aligned to the epoch.
+pub const SHARE_AGGREGATE_INTERVAL_SECONDS: i64 = 600;
+
+// Rolls the shares and rejections in [start_time, end_time) into their buckets and moves the watermark to
+// `end_time`, in one transaction so no share is counted twice. Both times should be on bucket boundaries.
+pub async fn aggregate(
+    pool: &Pool<Postgres>,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO share_aggregates
+        (miner_id, worker, bucket_start, sampling_difficulty, share_count)
+        SELECT miner_id, COALESCE(worker, ''),
+               TIMESTAMP 'epoch' + FLOOR(EXTRACT(EPOCH FROM created_at) / $3) * $3 * INTERVAL '1 second',
+               sampling_difficulty, COUNT(*)
+        FROM proof_of_work
+        WHERE created_at >= $1 AND created_at < $2
+        GROUP BY 1, 2, 3, 4
+        ON CONFLICT (miner_id, worker, bucket_start, sampling_difficulty)
+        DO UPDATE SET share_count = share_aggregates.share_count + EXCLUDED.share_count
+        "#,
+        start_time, end_time, SHARE_AGGREGATE_INTERVAL_SECONDS as f64
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO share_rejection_aggregates
+        (miner_id, worker, bucket_start, reason, share_count)
+        SELECT miner_id, COALESCE(worker, ''),
+               TIMESTAMP 'epoch' + FLOOR(EXTRACT(EPOCH FROM created_at) / $3) * $3 * INTERVAL '1 second',
+               reason, SUM(share_count)
+        FROM share_rejections
+        WHERE created_at >= $1 AND created_at < $2
+        GROUP BY 1, 2, 3, 4
+        ON CONFLICT (miner_id, worker, bucket_start, reason)
+        DO UPDATE SET share_count = share_rejection_aggregates.share_count + EXCLUDED.share_count
+        "#,
+        start_time, end_time, SHARE_AGGREGATE_INTERVAL_SECONDS as f64
+    )
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO watermarks (name, value, updated_at)
+        VALUES ($1, $2, NOW())
+        ON CONFLICT (name) DO UPDATE SET value = $2, updated_at = NOW()
+        "#,
+        SHARE_AGGREGATE_WATERMARK,
+        end_time.timestamp()
+    )
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Everything before this has been aggregated, and may be cleaned up.
+pub async fn get_aggregated_until(pool: &Pool<Postgres>) -> Result<Option<NaiveDateTime>, sqlx::Error> {
+    Ok(
+        watermark::get(pool, SHARE_AGGREGATE_WATERMARK)
+            .await?
+            .and_then(|value| NaiveDateTime::from_timestamp_opt(value, 0))
+    )
+}
+
+pub fn bucket_start(time: NaiveDateTime) -> NaiveDateTime {
+    let timestamp = time.timestamp();
+    let start = timestamp - timestamp.rem_euclid(SHARE_AGGREGATE_INTERVAL_SECONDS);
+
+    NaiveDateTime::from_timestamp_opt(start, 0).unwrap_or(time)
+}
+
+// The whole buckets from `start_time` to `end_time` that have been aggregated, as a half open range. Shares in
+// it are counted from the aggregates and the rest from the proofs themselves, so counts stay exact wherever
+// the proofs are still kept. The range is empty when no whole bucket has been aggregated.
+pub async fn get_aggregated_range(
+    pool: &Pool<Postgres>,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+) -> Result<(NaiveDateTime, NaiveDateTime), sqlx::Error> {
+    let aggregated_until = get_aggregated_until(pool).await?.unwrap_or_default();
+
+    let first_bucket = match bucket_start(start_time) {
+        bucket if bucket < start_time => bucket + chrono::Duration::seconds(SHARE_AGGREGATE_INTERVAL_SECONDS),
+        bucket => bucket,
+    };
+    let end_bucket = bucket_start(end_time).min(aggregated_until).max(first_bucket);
+
+    Ok((first_bucket, end_bucket))
+}
+
+pub async fn get_oldest_share_at(pool: &Pool<Postgres>) -> Result<Option<NaiveDateTime>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT LEAST(
+            (SELECT MIN(created_at) FROM proof_of_work),
+            (SELECT MIN(created_at) FROM share_rejections)
+        ) as oldest
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.oldest)
+}
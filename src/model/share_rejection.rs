@@ -0,0 +1,74 @@
+use chrono::NaiveDateTime;
+use sqlx::{Postgres, Pool};
+
+use super::share_aggregate::get_aggregated_range;
+
+pub struct NewShareRejection {
+    pub reason: String,
+    pub share_count: i32,
+}
+
+pub async fn create(
+    pool: &Pool<Postgres>,
+    miner_id: i32,
+    worker: Option<&str>,
+    block_number: i32,
+    rejections: &[NewShareRejection],
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    for rejection in rejections.iter() {
+        sqlx::query!(
+            r#"
+            INSERT INTO share_rejections
+            (miner_id, worker, block_number, reason, share_count, created_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            "#,
+            miner_id, worker, block_number, rejection.reason, rejection.share_count
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(rejections.len() as u64)
+}
+
+pub struct ShareRejectionCount {
+    pub reason: String,
+    pub share_count: i64,
+}
+
+// Rejections in whole buckets that have been aggregated are counted from the aggregates, as shares are.
+pub async fn count_by_time_range(
+    pool: &Pool<Postgres>,
+    miner_id: i32,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+) -> Result<Vec<ShareRejectionCount>, sqlx::Error> {
+    let (aggregated_from, aggregated_to) = get_aggregated_range(pool, start_time, end_time).await?;
+
+    sqlx::query_as!(
+        ShareRejectionCount,
+        r#"
+        SELECT reason as "reason!", SUM(share_count)::BIGINT as "share_count!"
+        FROM (
+            SELECT reason, share_count
+            FROM share_rejections
+            WHERE miner_id = $1
+              AND created_at BETWEEN $2 AND $3
+              AND NOT (created_at >= $4 AND created_at < $5)
+            UNION ALL
+            SELECT reason, share_count
+            FROM share_rejection_aggregates
+            WHERE miner_id = $1
+              AND bucket_start >= $4 AND bucket_start < $5
+        ) AS counts
+        GROUP BY reason
+        "#,
+        miner_id, start_time, end_time, aggregated_from, aggregated_to
+    )
+    .fetch_all(pool)
+    .await
+}
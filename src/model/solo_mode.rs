@@ -0,0 +1,117 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{Postgres, Pool};
+
+#[derive(Debug, Serialize)]
+pub struct SoloModeChange {
+    pub id: i32,
+    pub miner_id: i32,
+    pub change_number: i32,
+    pub solo: bool,
+    pub signature: String,
+    pub key: String,
+    pub created_at: NaiveDateTime,
+    pub applied_at: Option<NaiveDateTime>,
+}
+
+pub async fn get_changes(pool: &Pool<Postgres>, miner_id: i32) -> Result<Vec<SoloModeChange>, sqlx::Error> {
+    sqlx::query_as!(
+        SoloModeChange,
+        r#"
+        SELECT id, miner_id, change_number, solo, signature, key, created_at, applied_at
+        FROM solo_mode_changes
+        WHERE miner_id = $1
+        ORDER BY change_number DESC
+        "#,
+        miner_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_changes(pool: &Pool<Postgres>, miner_id: i32) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM solo_mode_changes
+        WHERE miner_id = $1
+        "#,
+        miner_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.count)
+}
+
+// The mode a miner will switch to when the next block is seen, if they asked for one.
+pub async fn get_pending(pool: &Pool<Postgres>, miner_id: i32) -> Result<Option<bool>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT solo
+        FROM solo_mode_changes
+        WHERE miner_id = $1 AND applied_at IS NULL
+        ORDER BY change_number DESC
+        LIMIT 1
+        "#,
+        miner_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| row.solo))
+}
+
+// Only records the change. It is applied by apply_pending. A change number that was already used means
+// the signature was for a change someone else made first, so it fails on the unique constraint.
+pub async fn request(
+    pool: &Pool<Postgres>,
+    miner_id: i32,
+    change_number: i32,
+    solo: bool,
+    signature: &str,
+    key: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO solo_mode_changes
+        (miner_id, change_number, solo, signature, key, created_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        "#,
+        miner_id,
+        change_number,
+        solo,
+        signature,
+        key
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Applies each miner's latest unapplied change. One statement, so a change requested while it runs is
+// either applied or left for next time, never marked applied without being applied.
+pub async fn apply_pending(pool: &Pool<Postgres>) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        WITH applied AS (
+            UPDATE solo_mode_changes
+            SET applied_at = NOW()
+            WHERE applied_at IS NULL
+            RETURNING miner_id, change_number, solo
+        )
+        UPDATE miners
+        SET solo = pending.solo
+        FROM (
+            SELECT DISTINCT ON (miner_id) miner_id, solo
+            FROM applied
+            ORDER BY miner_id, change_number DESC
+        ) AS pending
+        WHERE miners.id = pending.miner_id
+        "#,
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
@@ -0,0 +1,16 @@
+use sqlx::{Postgres, Pool};
+
+pub async fn get(pool: &Pool<Postgres>, name: &str) -> Result<Option<i64>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT value
+        FROM watermarks
+        WHERE name = $1
+        "#,
+        name
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| r.value))
+}
@@ -0,0 +1,93 @@
+use chrono::NaiveDateTime;
+use sqlx::{Postgres, Pool};
+
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub url: String,
+    pub event: String,
+    pub payload: String,
+    pub attempts: i32,
+}
+
+// One delivery per url, all due straight away.
+pub async fn create(pool: &Pool<Postgres>, urls: &[String], event: &str, payload: &str) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO webhook_deliveries
+        (url, event, payload, attempts, next_attempt_at, created_at)
+        SELECT url, $2, $3, 0, NOW(), NOW()
+        FROM UNNEST($1::TEXT[]) AS url
+        "#,
+        urls, event, payload
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+pub async fn get_due(pool: &Pool<Postgres>, limit: i64) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
+    sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+        SELECT id, url, event, payload, attempts
+        FROM webhook_deliveries
+        WHERE delivered_at IS NULL AND abandoned_at IS NULL AND next_attempt_at <= NOW()
+        ORDER BY next_attempt_at
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn mark_delivered(pool: &Pool<Postgres>, id: i32) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE webhook_deliveries
+        SET attempts = attempts + 1, delivered_at = NOW(), last_error = NULL
+        WHERE id = $1
+        "#,
+        id
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+// Records a failed attempt, to be retried at `next_attempt_at`, or given up on if there is none.
+pub async fn mark_failed(
+    pool: &Pool<Postgres>,
+    id: i32,
+    error: &str,
+    next_attempt_at: Option<NaiveDateTime>,
+) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE webhook_deliveries
+        SET attempts = attempts + 1,
+            last_error = $2,
+            next_attempt_at = COALESCE($3, next_attempt_at),
+            abandoned_at = CASE WHEN $3::TIMESTAMP IS NULL THEN NOW() END
+        WHERE id = $1
+        "#,
+        id, error, next_attempt_at
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+// Delivered and abandoned payloads are only kept around for a while, for debugging.
+pub async fn cleanup(pool: &Pool<Postgres>, before: NaiveDateTime) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM webhook_deliveries
+        WHERE COALESCE(delivered_at, abandoned_at) < $1
+        "#,
+        before
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
@@ -0,0 +1,81 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{Postgres, Pool};
+
+pub const WITHDRAWAL_WATERMARK: &str = "bank_withdrawals";
+
+#[derive(Debug, Serialize)]
+pub struct Withdrawal {
+    pub id: i32,
+    pub transaction_hash: String,
+    pub pkh: String,
+    pub amount: i64,
+    pub slot_no: i32,
+    pub created_at: NaiveDateTime,
+}
+
+pub struct NewWithdrawal {
+    pub transaction_hash: String,
+    pub pkh: String,
+    pub amount: i64,
+    pub slot_no: i32,
+}
+
+// Stores the withdrawals found in one spend of the bank and moves the watermark past it.
+pub async fn create(
+    pool: &Pool<Postgres>,
+    new_withdrawals: &[NewWithdrawal],
+    bank_slot_no: i32,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    for new_withdrawal in new_withdrawals.iter() {
+        sqlx::query!(
+            r#"
+            INSERT INTO withdrawals
+            (transaction_hash, pkh, amount, slot_no, created_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            ON CONFLICT (transaction_hash, pkh) DO NOTHING
+            "#,
+            new_withdrawal.transaction_hash,
+            new_withdrawal.pkh,
+            new_withdrawal.amount,
+            new_withdrawal.slot_no
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO watermarks (name, value, updated_at)
+        VALUES ($1, $2, NOW())
+        ON CONFLICT (name) DO UPDATE SET value = $2, updated_at = NOW()
+        "#,
+        WITHDRAWAL_WATERMARK,
+        bank_slot_no as i64
+    )
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn get_by_pkh(pool: &Pool<Postgres>, pkh: &str, limit: i64) -> Result<Vec<Withdrawal>, sqlx::Error> {
+    sqlx::query_as!(
+        Withdrawal,
+        r#"
+        SELECT id, transaction_hash, pkh, amount, slot_no, created_at
+        FROM withdrawals
+        WHERE pkh = $1
+        ORDER BY slot_no DESC
+        LIMIT $2
+        "#,
+        pkh,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
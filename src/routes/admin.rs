@@ -0,0 +1,231 @@
+use actix_web::{delete, get, put, web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    common::GenericMessageResponse,
+    model::{
+        fee_override::{self, get_fee_overrides},
+        miner::{get_miner_by_id, set_banned},
+        reconciliation::get_discrepancies,
+    },
+    service::{
+        fee::FeePolicy,
+        webhook::{notify, WebhookEvent},
+    },
+};
+
+// Admin endpoints are disabled unless ADMIN_TOKEN is set, and then require it as a bearer token.
+fn unauthorized(req: &HttpRequest) -> Option<HttpResponse> {
+    let Ok(admin_token) = std::env::var("ADMIN_TOKEN") else {
+        return Some(HttpResponse::Forbidden().json(GenericMessageResponse {
+            message: String::from("Admin endpoints are disabled."),
+        }));
+    };
+
+    let bearer_token = req.headers()
+        .get("Authorization")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "));
+
+    if admin_token.is_empty() || bearer_token != Some(admin_token.as_str()) {
+        return Some(HttpResponse::Unauthorized().json(GenericMessageResponse {
+            message: String::from("Invalid admin token."),
+        }));
+    }
+
+    None
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscrepanciesRequest {
+    limit: Option<i64>,
+}
+
+#[get("/admin/discrepancies")]
+async fn discrepancies(
+    req: HttpRequest,
+    pool: web::Data<Pool<Postgres>>,
+    query: web::Query<DiscrepanciesRequest>,
+) -> impl Responder {
+    if let Some(response) = unauthorized(&req) {
+        return response;
+    }
+
+    match get_discrepancies(&pool, query.limit.unwrap_or(100)).await {
+        Ok(discrepancies) => HttpResponse::Ok().json(discrepancies),
+        Err(_) => HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch reconciliation discrepancies."),
+        }),
+    }
+}
+
+#[get("/admin/fee-overrides")]
+async fn fee_overrides(
+    req: HttpRequest,
+    pool: web::Data<Pool<Postgres>>,
+) -> impl Responder {
+    if let Some(response) = unauthorized(&req) {
+        return response;
+    }
+
+    match get_fee_overrides(&pool).await {
+        Ok(fee_overrides) => HttpResponse::Ok().json(fee_overrides),
+        Err(_) => HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch fee overrides."),
+        }),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FeeOverrideRequest {
+    policy: String,
+    note: Option<String>,
+}
+
+#[put("/admin/fee-overrides/{miner_id}")]
+async fn set_fee_override(
+    req: HttpRequest,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<i32>,
+    body: web::Json<FeeOverrideRequest>,
+) -> impl Responder {
+    if let Some(response) = unauthorized(&req) {
+        return response;
+    }
+
+    let miner_id = path.into_inner();
+
+    // Stored as given, but only once it is known to parse.
+    let policy: FeePolicy = match body.policy.parse() {
+        Ok(policy) => policy,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(GenericMessageResponse {
+                message: format!("Invalid fee policy: {}", e.0),
+            });
+        }
+    };
+
+    match get_miner_by_id(&pool, miner_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json(GenericMessageResponse {
+                message: format!("No miner with id {}", miner_id),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to retrieve miner."),
+            });
+        }
+    }
+
+    match fee_override::upsert(&pool, miner_id, &policy.to_string(), body.note.as_deref()).await {
+        Ok(fee_override) => HttpResponse::Ok().json(fee_override),
+        Err(_) => HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to save fee override."),
+        }),
+    }
+}
+
+#[delete("/admin/fee-overrides/{miner_id}")]
+async fn delete_fee_override(
+    req: HttpRequest,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if let Some(response) = unauthorized(&req) {
+        return response;
+    }
+
+    let miner_id = path.into_inner();
+
+    match fee_override::delete(&pool, miner_id).await {
+        Ok(0) => HttpResponse::NotFound().json(GenericMessageResponse {
+            message: format!("Miner {} has no fee override", miner_id),
+        }),
+        Ok(_) => HttpResponse::Ok().json(GenericMessageResponse {
+            message: format!("Removed fee override for miner {}", miner_id),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to remove fee override."),
+        }),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MinerBannedEvent {
+    miner_id: i32,
+    address: String,
+}
+
+// Banning a miner turns away their shares from then on. Shares they already submitted are still paid.
+#[put("/admin/miners/{miner_id}/ban")]
+async fn ban_miner(
+    req: HttpRequest,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if let Some(response) = unauthorized(&req) {
+        return response;
+    }
+
+    let miner_id = path.into_inner();
+
+    let miner = match get_miner_by_id(&pool, miner_id).await {
+        Ok(Some(miner)) => miner,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(GenericMessageResponse {
+                message: format!("No miner with id {}", miner_id),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to retrieve miner."),
+            });
+        }
+    };
+
+    if miner.banned {
+        return HttpResponse::Ok().json(GenericMessageResponse {
+            message: format!("Miner {} is already banned", miner_id),
+        });
+    }
+
+    if set_banned(&pool, miner_id, true).await.is_err() {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to ban miner."),
+        });
+    }
+
+    notify(&pool, WebhookEvent::MinerBanned, &MinerBannedEvent { miner_id, address: miner.address }).await;
+
+    HttpResponse::Ok().json(GenericMessageResponse {
+        message: format!("Banned miner {}", miner_id),
+    })
+}
+
+#[delete("/admin/miners/{miner_id}/ban")]
+async fn unban_miner(
+    req: HttpRequest,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    if let Some(response) = unauthorized(&req) {
+        return response;
+    }
+
+    let miner_id = path.into_inner();
+
+    match set_banned(&pool, miner_id, false).await {
+        Ok(Some(_)) => HttpResponse::Ok().json(GenericMessageResponse {
+            message: format!("Unbanned miner {}", miner_id),
+        }),
+        Ok(None) => HttpResponse::NotFound().json(GenericMessageResponse {
+            message: format!("No miner with id {}", miner_id),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to unban miner."),
+        }),
+    }
+}
@@ -0,0 +1,62 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    address,
+    common::GenericMessageResponse,
+    model::payout::sum_pending_by_pkh,
+    service::bank::get_current_bank,
+};
+
+#[derive(Debug, Deserialize)]
+struct BalanceRequest {
+    address: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BalanceResponse {
+    address: String,
+    pkh: String,
+    banked: u64,
+    pending: u64,
+    bank_slot: i32,
+}
+
+#[get("/balance")]
+async fn balance(
+    pool: web::Data<Pool<Postgres>>,
+    query: web::Query<BalanceRequest>,
+) -> impl Responder {
+    let Ok(pkh) = address::pkh_from_address(&query.address) else {
+        return HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: format!("Could not create a valid public key hash for address {}", query.address),
+        });
+    };
+
+    let bank = match get_current_bank().await {
+        Ok(bank) => bank,
+        Err(e) => {
+            log::error!("Failed to read the pool bank: {}", e);
+            return HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Could not read the pool bank."),
+            });
+        }
+    };
+
+    // Both are for the payment key hash, so pending moves into banked once its datum confirms. A miner with
+    // a payout address or splits sees their rewards under those addresses, not their mining address.
+    let Ok(pending) = sum_pending_by_pkh(&pool, &pkh).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch pending payouts."),
+        });
+    };
+
+    HttpResponse::Ok().json(BalanceResponse {
+        address: query.address.clone(),
+        banked: bank.owners.get(&pkh).copied().unwrap_or(0),
+        pending: pending as u64,
+        bank_slot: bank.slot_no,
+        pkh,
+    })
+}
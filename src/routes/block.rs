@@ -0,0 +1,88 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    common::GenericMessageResponse,
+    model::datum_submission::{count_by_status, get_found_blocks, DatumStatus, FoundBlock},
+};
+
+const DEFAULT_BLOCKS_LIMIT: i64 = 50;
+const MAX_BLOCKS_LIMIT: i64 = 500;
+
+#[derive(Debug, Deserialize)]
+struct BlocksRequest {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct BlocksResponse {
+    total: i64,
+    limit: i64,
+    offset: i64,
+    blocks: Vec<BlockResponse>,
+}
+
+#[derive(Debug, Serialize)]
+struct BlockResponse {
+    block_number: i32,
+    transaction_hash: String,
+    sha: String,
+    finder_address: String,
+    status: DatumStatus,
+    created_at: NaiveDateTime,
+    confirmed_in_slot: Option<i32>,
+    confirmed_at: Option<NaiveDateTime>,
+    total_payout: i64,
+}
+
+impl From<FoundBlock> for BlockResponse {
+    fn from(block: FoundBlock) -> Self {
+        BlockResponse {
+            block_number: block.block_number,
+            transaction_hash: block.transaction_hash,
+            sha: block.sha,
+            finder_address: block.finder_address,
+            status: DatumStatus::new(block.rejected, block.orphaned, block.confirmed_in_slot.is_some()),
+            created_at: block.created_at,
+            confirmed_in_slot: block.confirmed_in_slot,
+            confirmed_at: block.confirmed_at,
+            total_payout: block.total_payout,
+        }
+    }
+}
+
+#[get("/blocks")]
+async fn blocks(
+    pool: web::Data<Pool<Postgres>>,
+    query: web::Query<BlocksRequest>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(DEFAULT_BLOCKS_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if !(1..=MAX_BLOCKS_LIMIT).contains(&limit) || offset < 0 {
+        return HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: format!("limit must be between 1 and {}, and offset cannot be negative.", MAX_BLOCKS_LIMIT),
+        });
+    }
+
+    let Ok(counts) = count_by_status(&pool).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to count blocks found."),
+        });
+    };
+
+    let Ok(found_blocks) = get_found_blocks(&pool, limit, offset).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch blocks found."),
+        });
+    };
+
+    HttpResponse::Ok().json(BlocksResponse {
+        total: counts.found,
+        limit,
+        offset,
+        blocks: found_blocks.into_iter().map(|block| block.into()).collect(),
+    })
+}
@@ -3,7 +3,19 @@ use chrono::{NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{Postgres, Pool};
 
-use crate::{common::GenericMessageResponse, model::proof_of_work::{self, MinerProofCount}};
+use crate::{
+    address,
+    common::GenericMessageResponse,
+    model::{
+        miner::get_miner_by_pkh,
+        proof_of_work::{self, MinerProofCount},
+        share_aggregate::{get_aggregated_until, SHARE_AGGREGATE_INTERVAL_SECONDS},
+    },
+};
+
+const DEFAULT_HISTORY_INTERVAL_SECONDS: i64 = 3600;
+const MIN_HISTORY_INTERVAL_SECONDS: i64 = 60;
+const MAX_HISTORY_BUCKETS: i64 = 1000;
 
 #[derive(Debug, Deserialize)]
 struct HashrateRequest {
@@ -12,11 +24,6 @@ struct HashrateRequest {
     end_time: Option<u64>
 }
 
-#[derive(Debug, Serialize)]
-struct HashrateResponse {
-    estimated_hash_rate: f64
-}
-
 #[get("/hashrate")]
 async fn hashrate(
     pool: web::Data<Pool<Postgres>>,
@@ -47,27 +54,338 @@ async fn hashrate(
     };
     
     HttpResponse::Ok().json(
-        HashrateResponse { 
-            estimated_hash_rate: estimate_hashrate(pow, start_time.unwrap(), end_time.unwrap())
-        }
+        estimate_hashrate_with_bounds(&pow, start_time.unwrap(), end_time.unwrap())
     )
 }
 
+#[derive(Debug, Deserialize)]
+struct HashrateHistoryRequest {
+    address: Option<String>,
+    worker: Option<String>,
+    interval: Option<i64>,
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct HashrateHistoryResponse {
+    address: Option<String>,
+    worker: Option<String>,
+    interval: i64,
+    from: i64,
+    to: i64,
+    buckets: Vec<HashrateBucket>,
+}
+
+#[derive(Debug, Serialize)]
+struct HashrateBucket {
+    start_time: i64,
+    share_count: i64,
+    estimated_hash_rate: f64,
+}
+
+#[get("/hashrate/history")]
+async fn history(
+    pool: web::Data<Pool<Postgres>>,
+    query: web::Query<HashrateHistoryRequest>,
+) -> impl Responder {
+    let requested_interval = query.interval.unwrap_or(DEFAULT_HISTORY_INTERVAL_SECONDS);
+    if requested_interval < MIN_HISTORY_INTERVAL_SECONDS {
+        return HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: format!("interval must be at least {} seconds.", MIN_HISTORY_INTERVAL_SECONDS),
+        });
+    }
+
+    let to = query.to.unwrap_or(Utc::now().timestamp());
+    let requested_from = query.from.unwrap_or(to - 86400);
+
+    let Ok(aggregated_until) = get_aggregated_until(&pool).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch proofs of work."),
+        });
+    };
+
+    // Shares before the watermark are counted from aggregate buckets, which can't be split any finer.
+    let interval = if aggregated_until.is_some_and(|aggregated_until| requested_from < aggregated_until.timestamp()) {
+        (requested_interval + SHARE_AGGREGATE_INTERVAL_SECONDS - 1) / SHARE_AGGREGATE_INTERVAL_SECONDS * SHARE_AGGREGATE_INTERVAL_SECONDS
+    } else {
+        requested_interval
+    };
+
+    // Buckets are aligned to the interval, so the first one is widened to start on a boundary.
+    let from = requested_from - requested_from.rem_euclid(interval);
+    if from >= to {
+        return HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: String::from("from must be before to."),
+        });
+    }
+
+    if (to - from) / interval >= MAX_HISTORY_BUCKETS {
+        return HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: format!("At most {} buckets can be requested at once. Use a longer interval.", MAX_HISTORY_BUCKETS),
+        });
+    }
+
+    let (Some(start_time), Some(end_time)) = (
+        NaiveDateTime::from_timestamp_opt(from, 0),
+        NaiveDateTime::from_timestamp_opt(to, 0),
+    ) else {
+        return HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: String::from("Timestamp input was invalid."),
+        });
+    };
+
+    let miner_id = match &query.address {
+        Some(miner_address) => {
+            let Ok(pkh) = address::pkh_from_address(miner_address) else {
+                return HttpResponse::BadRequest().json(GenericMessageResponse {
+                    message: format!("Could not create a valid public key hash for address {}", miner_address),
+                });
+            };
+
+            match get_miner_by_pkh(&pool, &pkh).await {
+                Ok(Some(miner)) => Some(miner.id),
+                Ok(None) => {
+                    return HttpResponse::NotFound().json(GenericMessageResponse {
+                        message: format!("No miner found for address {}", miner_address),
+                    });
+                }
+                Err(_) => {
+                    return HttpResponse::InternalServerError().json(GenericMessageResponse {
+                        message: String::from("Failed to retrieve miner."),
+                    });
+                }
+            }
+        }
+        None if query.worker.is_some() => {
+            return HttpResponse::BadRequest().json(GenericMessageResponse {
+                message: String::from("worker can only be given with an address."),
+            });
+        }
+        None => None,
+    };
+
+    let Ok(bucket_counts) = proof_of_work::count_by_interval(
+        &pool, miner_id, query.worker.as_deref(), start_time, end_time, interval
+    ).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch proofs of work."),
+        });
+    };
+
+    // Every bucket is reported, including empty ones, so charts don't skip over idle periods.
+    let mut buckets: Vec<HashrateBucket> = (from..to)
+        .step_by(interval as usize)
+        .map(|start_time| HashrateBucket { start_time, share_count: 0, estimated_hash_rate: 0.0 })
+        .collect();
+
+    for bucket_count in bucket_counts {
+        let index = ((bucket_count.bucket - from) / interval) as usize;
+        let Some(bucket) = buckets.get_mut(index) else {
+            continue;
+        };
+
+        bucket.share_count += bucket_count.proof_count;
+        bucket.estimated_hash_rate += estimate_hashes_for_difficulty(
+            bucket_count.proof_count as usize,
+            bucket_count.sampling_difficulty as u8,
+        );
+    }
+
+    // Hashes are spread over the part of the bucket that has passed, which is less than the interval for the last one.
+    for bucket in buckets.iter_mut() {
+        let seconds = (bucket.start_time + interval).min(to) - bucket.start_time;
+        bucket.estimated_hash_rate = if seconds > 0 { bucket.estimated_hash_rate / seconds as f64 } else { 0.0 };
+    }
+
+    HttpResponse::Ok().json(HashrateHistoryResponse {
+        address: query.address.clone(),
+        worker: query.worker.clone(),
+        interval,
+        from,
+        to,
+        buckets,
+    })
+}
+
 pub fn estimate_hashes_for_difficulty(proof_count: usize, zeros: u8) -> f64 {
     let estimated_proofs_per_proof: f64 = 16f64.powi(zeros as i32);  
     (proof_count as f64) * estimated_proofs_per_proof
 }
 
+// A hash meets the target when it has more leading zeroes than the block asks for, or exactly as many and the
+// four hex digits after them are below the difficulty number.
+pub fn expected_hashes_for_target(leading_zeroes: u8, difficulty_number: u16) -> f64 {
+    16f64.powi(leading_zeroes as i32) * 65536.0 / (difficulty_number.max(1) as f64)
+}
+
 pub fn estimate_hashrate(
     proofs: Vec<MinerProofCount>,
     start_time: NaiveDateTime,
     end_time: NaiveDateTime,
 ) -> f64 {
-    let duration = end_time - start_time;
+    estimate_hashrate_with_bounds(&proofs, start_time, end_time).estimated_hash_rate
+}
+
+/// The z-score for two-sided 95% confidence bounds.
+const CONFIDENCE_Z: f64 = 1.959964;
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct HashrateEstimate {
+    pub estimated_hash_rate: f64,
+    pub lower_bound: f64,
+    /// Unknown when no shares were seen, since there is then nothing to say how much work a share stands for.
+    pub upper_bound: Option<f64>,
+}
+
+// Shares arrive as a Poisson process at the hash rate over 16^zeros, so the number seen bounds the hash rate the
+// way any Poisson count bounds its mean. Shares at different sampling difficulties are folded into one count with
+// the same relative variance. An empty or backwards window has no hash rate to speak of.
+pub fn estimate_hashrate_with_bounds(
+    proofs: &[MinerProofCount],
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+) -> HashrateEstimate {
+    let seconds = (end_time - start_time).num_milliseconds() as f64 / 1000.0;
+    if seconds <= 0.0 {
+        return HashrateEstimate::default();
+    }
+
+    let (total_hashes, total_squared_hashes) = proofs.iter().fold((0.0, 0.0), |(hashes, squared_hashes), proof| {
+        let hashes_per_proof = estimate_hashes_for_difficulty(1, proof.sampling_difficulty as u8);
+        (
+            hashes + hashes_per_proof * proof.proof_count as f64,
+            squared_hashes + hashes_per_proof * hashes_per_proof * proof.proof_count as f64,
+        )
+    });
 
-    let total_hashes: f64 = proofs.into_iter().map(|proof| {
-        estimate_hashes_for_difficulty(proof.proof_count as usize, proof.sampling_difficulty as u8)
-    }).sum();
+    if total_hashes <= 0.0 {
+        return HashrateEstimate::default();
+    }
 
-    total_hashes / duration.num_seconds() as f64
-}
\ No newline at end of file
+    let effective_count = total_hashes * total_hashes / total_squared_hashes;
+    let hashes_per_count = total_squared_hashes / total_hashes;
+
+    HashrateEstimate {
+        estimated_hash_rate: total_hashes / seconds,
+        lower_bound: poisson_lower_bound(effective_count) * hashes_per_count / seconds,
+        upper_bound: Some(poisson_upper_bound(effective_count) * hashes_per_count / seconds),
+    }
+}
+
+// Bounds on a Poisson mean from the chi-squared quantiles, using the Wilson-Hilferty approximation.
+// It is within a percent of the exact bounds from a handful of shares up, and errs wide below that.
+fn poisson_lower_bound(count: f64) -> f64 {
+    if count <= 0.0 {
+        return 0.0;
+    }
+
+    let cube_root = 1.0 - 1.0 / (9.0 * count) - CONFIDENCE_Z / (3.0 * count.sqrt());
+    (count * cube_root.max(0.0).powi(3)).max(0.0)
+}
+
+fn poisson_upper_bound(count: f64) -> f64 {
+    let count = count + 1.0;
+    let cube_root = 1.0 - 1.0 / (9.0 * count) + CONFIDENCE_Z / (3.0 * count.sqrt());
+    count * cube_root.powi(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn proofs(proof_count: i64, sampling_difficulty: i32) -> MinerProofCount {
+        MinerProofCount {
+            miner_id: 1,
+            miner_address: String::from("addr1"),
+            proof_count,
+            sampling_difficulty,
+        }
+    }
+
+    fn window(seconds: i64) -> (NaiveDateTime, NaiveDateTime) {
+        let start_time = NaiveDateTime::default();
+        (start_time, start_time + Duration::seconds(seconds))
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= expected * tolerance, "{} is not within {} of {}", actual, tolerance, expected);
+    }
+
+    #[test]
+    fn no_shares_have_no_hash_rate() {
+        let (start_time, end_time) = window(60);
+        let estimate = estimate_hashrate_with_bounds(&[], start_time, end_time);
+
+        assert_eq!(estimate.estimated_hash_rate, 0.0);
+        assert_eq!(estimate.lower_bound, 0.0);
+        assert_eq!(estimate.upper_bound, None);
+    }
+
+    #[test]
+    fn empty_and_backwards_windows_have_no_hash_rate() {
+        let (start_time, end_time) = window(60);
+
+        for (start_time, end_time) in [(start_time, start_time), (end_time, start_time)] {
+            let estimate = estimate_hashrate_with_bounds(&[proofs(10, 8)], start_time, end_time);
+
+            assert_eq!(estimate.estimated_hash_rate, 0.0);
+            assert_eq!(estimate.upper_bound, None);
+        }
+    }
+
+    #[test]
+    fn estimate_is_hashes_over_time() {
+        let (start_time, end_time) = window(100);
+        let estimate = estimate_hashrate_with_bounds(&[proofs(10, 2), proofs(5, 3)], start_time, end_time);
+
+        assert_eq!(estimate.estimated_hash_rate, (10.0 * 256.0 + 5.0 * 4096.0) / 100.0);
+        assert!(estimate.lower_bound < estimate.estimated_hash_rate);
+        assert!(estimate.upper_bound.unwrap() > estimate.estimated_hash_rate);
+    }
+
+    // Exact two-sided 95% bounds on a Poisson mean, for comparison.
+    const EXACT_BOUNDS: [(i64, f64, f64); 4] = [
+        (1, 0.0253, 5.5716),
+        (2, 0.2422, 7.2247),
+        (10, 4.7954, 18.3904),
+        (100, 81.3639, 121.6299),
+    ];
+
+    #[test]
+    fn bounds_are_close_to_exact_from_a_handful_of_shares() {
+        let (start_time, end_time) = window(1);
+
+        for (count, lower, upper) in EXACT_BOUNDS.into_iter().filter(|(count, _, _)| *count >= 10) {
+            let estimate = estimate_hashrate_with_bounds(&[proofs(count, 0)], start_time, end_time);
+
+            assert_close(estimate.lower_bound, lower, 0.01);
+            assert_close(estimate.upper_bound.unwrap(), upper, 0.01);
+        }
+    }
+
+    #[test]
+    fn bounds_err_wide_at_small_counts() {
+        let (start_time, end_time) = window(1);
+
+        for (count, lower, upper) in EXACT_BOUNDS {
+            let estimate = estimate_hashrate_with_bounds(&[proofs(count, 0)], start_time, end_time);
+
+            assert!(estimate.lower_bound >= 0.0);
+            assert!(estimate.lower_bound <= lower * 1.01, "lower bound {} for {} shares", estimate.lower_bound, count);
+            assert!(estimate.upper_bound.unwrap() >= upper * 0.99, "upper bound {:?} for {} shares", estimate.upper_bound, count);
+        }
+    }
+
+    #[test]
+    fn bounds_scale_with_sampling_difficulty() {
+        let (start_time, end_time) = window(1);
+        let easy = estimate_hashrate_with_bounds(&[proofs(10, 0)], start_time, end_time);
+        let hard = estimate_hashrate_with_bounds(&[proofs(10, 4)], start_time, end_time);
+
+        assert_close(hard.lower_bound, easy.lower_bound * 65536.0, 1e-9);
+        assert_close(hard.upper_bound.unwrap(), easy.upper_bound.unwrap() * 65536.0, 1e-9);
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use actix_web::{get, web, HttpResponse, Responder};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    common::DATABASE_MAX_CONNECTIONS,
+    model::{datum_submission::count_unconfirmed, reconciliation::count_discrepancies},
+    service::{
+        block::BlockService,
+        metrics::{
+            HistogramSnapshot, CANDIDATE_SUBMISSIONS, KUPO_REQUEST_ERRORS, RATE_LIMITED_SUBMISSIONS,
+            SHARES_ACCEPTED, SHARES_REJECTED, SUBMIT_LATENCY,
+        },
+    },
+};
+
+// Prometheus text exposition format.
+fn write_metric(body: &mut String, name: &str, metric_type: &str, help: &str, value: f64) {
+    let _ = writeln!(body, "# HELP {} {}", name, help);
+    let _ = writeln!(body, "# TYPE {} {}", name, metric_type);
+    let _ = writeln!(body, "{} {}", name, value);
+}
+
+fn write_labelled_metric(
+    body: &mut String,
+    name: &str,
+    metric_type: &str,
+    help: &str,
+    label: &str,
+    values: &[(&str, f64)],
+) {
+    let _ = writeln!(body, "# HELP {} {}", name, help);
+    let _ = writeln!(body, "# TYPE {} {}", name, metric_type);
+    for (label_value, value) in values {
+        let _ = writeln!(body, "{}{{{}=\"{}\"}} {}", name, label, label_value, value);
+    }
+}
+
+fn write_histogram(body: &mut String, name: &str, help: &str, histogram: HistogramSnapshot) {
+    let _ = writeln!(body, "# HELP {} {}", name, help);
+    let _ = writeln!(body, "# TYPE {} histogram", name);
+    for (bound, count) in histogram.buckets {
+        let _ = writeln!(body, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+    }
+    let _ = writeln!(body, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+    let _ = writeln!(body, "{}_sum {}", name, histogram.sum_seconds);
+    let _ = writeln!(body, "{}_count {}", name, histogram.count);
+}
+
+#[get("/metrics")]
+async fn metrics(
+    pool: web::Data<Pool<Postgres>>,
+    block_service: web::Data<Arc<BlockService>>,
+) -> impl Responder {
+    let mut body = String::new();
+
+    match count_discrepancies(&pool).await {
+        Ok(discrepancies) => write_metric(
+            &mut body,
+            "tunapond_reconciliation_discrepancies",
+            "gauge",
+            "Differences found between recorded payouts and the bank datum on chain.",
+            discrepancies as f64,
+        ),
+        Err(e) => log::error!("Failed to count reconciliation discrepancies: {:?}", e),
+    }
+
+    match count_unconfirmed(&pool).await {
+        Ok(unconfirmed) => write_metric(
+            &mut body,
+            "tunapond_unconfirmed_datums",
+            "gauge",
+            "Datums submitted but not yet seen on chain or rejected.",
+            unconfirmed as f64,
+        ),
+        Err(e) => log::error!("Failed to count unconfirmed datums: {:?}", e),
+    }
+
+    write_metric(
+        &mut body,
+        "tunapond_shares_accepted_total",
+        "counter",
+        "Shares accepted since tunapond started.",
+        SHARES_ACCEPTED.get() as f64,
+    );
+
+    let rejected: Vec<(&str, f64)> = SHARES_REJECTED.get()
+        .into_iter()
+        .map(|(reason, count)| (reason, count as f64))
+        .collect();
+    write_labelled_metric(
+        &mut body,
+        "tunapond_shares_rejected_total",
+        "counter",
+        "Shares rejected since tunapond started, by reason.",
+        "reason",
+        &rejected,
+    );
+
+    write_histogram(
+        &mut body,
+        "tunapond_submit_duration_seconds",
+        "Time taken to handle a POST /submit.",
+        SUBMIT_LATENCY.get(),
+    );
+
+    write_metric(
+        &mut body,
+        "tunapond_rate_limited_submissions_total",
+        "counter",
+        "Submissions turned away for exceeding MAX_SUBMISSIONS_PER_MINUTE.",
+        RATE_LIMITED_SUBMISSIONS.get() as f64,
+    );
+
+    let candidates: Vec<(&str, f64)> = CANDIDATE_SUBMISSIONS.get()
+        .into_iter()
+        .map(|(outcome, count)| (outcome, count as f64))
+        .collect();
+    write_labelled_metric(
+        &mut body,
+        "tunapond_candidate_submissions_total",
+        "counter",
+        "Shares that met the block's target, by whether their datum was submitted.",
+        "outcome",
+        &candidates,
+    );
+
+    write_metric(
+        &mut body,
+        "tunapond_kupo_request_errors_total",
+        "counter",
+        "Requests to Kupo that failed or returned something unreadable.",
+        KUPO_REQUEST_ERRORS.get() as f64,
+    );
+
+    if let Ok(block) = block_service.get_latest() {
+        write_metric(
+            &mut body,
+            "tunapond_block_number",
+            "gauge",
+            "The latest block the block service has seen, or 0 before it has seen one.",
+            block.block_number as f64,
+        );
+    }
+
+    if let Some(seconds) = block_service.seconds_since_update() {
+        write_metric(
+            &mut body,
+            "tunapond_block_update_age_seconds",
+            "gauge",
+            "Seconds since the block service last fetched from Kupo successfully.",
+            seconds,
+        );
+    }
+
+    let idle_connections = pool.num_idle();
+    write_labelled_metric(
+        &mut body,
+        "tunapond_db_connections",
+        "gauge",
+        "Database connections open in the pool, by whether they are in use.",
+        "state",
+        &[
+            ("idle", idle_connections as f64),
+            ("in_use", pool.size().saturating_sub(idle_connections as u32) as f64),
+        ],
+    );
+
+    write_metric(
+        &mut body,
+        "tunapond_db_max_connections",
+        "gauge",
+        "The most connections the database pool will open.",
+        DATABASE_MAX_CONNECTIONS as f64,
+    );
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(body)
+}
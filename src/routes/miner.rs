@@ -0,0 +1,578 @@
+use std::collections::HashMap;
+
+use actix_web::{get, post, web, HttpResponse, Responder};
+use chrono::{Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    address,
+    common::GenericMessageResponse,
+    model::{
+        datum_submission::count_found_by_miner,
+        miner::{get_miner_by_pkh, Miner},
+        payout::{self, Payout},
+        proof_of_work::{count_by_time_range, count_workers_by_time_range, get_last_share_at},
+        payout_address::{get_changes, PayoutAddressChange},
+        payout_split::{self, PayoutSplit, PayoutSplitChange},
+        share_rejection,
+        solo_mode::{get_changes as get_solo_mode_changes, get_pending as get_pending_solo_mode, SoloModeChange},
+        withdrawal::{self, Withdrawal},
+    },
+    routes::hashrate::{estimate_hashes_for_difficulty, estimate_hashrate},
+    service::{
+        payout_address::{change_payout_address, next_change_number, payout_address_message, PayoutAddressError},
+        payout_split::{
+            change_payout_splits, next_change_number as next_split_change_number, parse_splits, payout_split_message,
+            NewPayoutSplit, PayoutSplitError,
+        },
+        proof_of_work::ShareRejectionReason,
+        solo_mode::{change_solo_mode, next_change_number as next_solo_change_number, solo_mode_message, SoloModeError},
+    },
+};
+
+/// The windows, in seconds, that hash rate and share counts are reported over.
+const STATS_WINDOWS: [i64; 3] = [600, 3600, 86400];
+
+#[derive(Debug, Serialize)]
+struct MinerStatsResponse {
+    miner_id: i32,
+    address: String,
+    pkh: String,
+    sampling_difficulty: i32,
+    solo: bool,
+    banned: bool,
+    last_share_at: Option<NaiveDateTime>,
+    blocks_found: i64,
+    windows: Vec<MinerStatsWindow>,
+    workers: Vec<WorkerStats>,
+}
+
+#[derive(Debug, Serialize)]
+struct MinerStatsWindow {
+    window_seconds: i64,
+    estimated_hash_rate: f64,
+    accepted: i64,
+    /// Every share turned away other than stale ones, broken down by reason in `rejections`.
+    rejected: i64,
+    stale: i64,
+    rejections: HashMap<String, i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct WorkerStats {
+    worker: Option<String>,
+    share_count: i64,
+    estimated_hash_rate: f64,
+    last_share_at: NaiveDateTime,
+}
+
+#[get("/miners/{address}")]
+async fn stats(
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let miner_address = path.into_inner();
+
+    let Ok(pkh) = address::pkh_from_address(&miner_address) else {
+        return HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: format!("Could not create a valid public key hash for address {}", miner_address),
+        });
+    };
+
+    let miner = match get_miner_by_pkh(&pool, &pkh).await {
+        Ok(Some(miner)) => miner,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(GenericMessageResponse {
+                message: format!("No miner found for address {}", miner_address),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to retrieve miner."),
+            });
+        }
+    };
+
+    match miner_stats(&pool, miner).await {
+        Ok(stats) => HttpResponse::Ok().json(stats),
+        Err(e) => {
+            log::error!("Failed to fetch stats for {}: {:?}", miner_address, e);
+            HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to fetch miner stats."),
+            })
+        }
+    }
+}
+
+async fn miner_stats(pool: &Pool<Postgres>, miner: Miner) -> Result<MinerStatsResponse, sqlx::Error> {
+    let now = Utc::now().naive_utc();
+
+    let mut windows: Vec<MinerStatsWindow> = Vec::new();
+    for window_seconds in STATS_WINDOWS {
+        let start_time = now - Duration::seconds(window_seconds);
+
+        let proof_counts = count_by_time_range(pool, Some(miner.id), start_time, now).await?;
+        let accepted: i64 = proof_counts.iter().map(|proof_count| proof_count.proof_count).sum();
+
+        let rejections: HashMap<String, i64> = share_rejection::count_by_time_range(pool, miner.id, start_time, now)
+            .await?
+            .into_iter()
+            .map(|rejection| (rejection.reason, rejection.share_count))
+            .collect();
+        let stale = rejections.get(ShareRejectionReason::Stale.name()).copied().unwrap_or(0);
+
+        windows.push(MinerStatsWindow {
+            window_seconds,
+            estimated_hash_rate: estimate_hashrate(proof_counts, start_time, now),
+            accepted,
+            rejected: rejections.values().sum::<i64>() - stale,
+            stale,
+            rejections,
+        });
+    }
+
+    // Workers are reported over the longest window.
+    let workers_window_seconds = STATS_WINDOWS[STATS_WINDOWS.len() - 1];
+    let workers_start_time = now - Duration::seconds(workers_window_seconds);
+
+    let mut workers: Vec<WorkerStats> = Vec::new();
+    for worker_count in count_workers_by_time_range(pool, miner.id, workers_start_time, now).await? {
+        let estimated_hash_rate = estimate_hashes_for_difficulty(
+            worker_count.proof_count as usize,
+            worker_count.sampling_difficulty as u8,
+        ) / workers_window_seconds as f64;
+
+        match workers.iter_mut().find(|worker| worker.worker == worker_count.worker) {
+            Some(worker) => {
+                worker.share_count += worker_count.proof_count;
+                worker.estimated_hash_rate += estimated_hash_rate;
+                worker.last_share_at = worker.last_share_at.max(worker_count.last_share_at);
+            }
+            None => workers.push(WorkerStats {
+                worker: worker_count.worker,
+                share_count: worker_count.proof_count,
+                estimated_hash_rate,
+                last_share_at: worker_count.last_share_at,
+            }),
+        }
+    }
+    workers.sort_by(|a, b| a.worker.cmp(&b.worker));
+
+    Ok(MinerStatsResponse {
+        last_share_at: get_last_share_at(pool, miner.id).await?,
+        blocks_found: count_found_by_miner(pool, miner.id).await?,
+        miner_id: miner.id,
+        address: miner.address,
+        pkh: miner.pkh,
+        sampling_difficulty: miner.sampling_difficulty,
+        solo: miner.solo,
+        banned: miner.banned,
+        windows,
+        workers,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryRequest {
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct HistoryResponse {
+    address: String,
+    pkh: String,
+    payouts: Vec<Payout>,
+    withdrawals: Vec<Withdrawal>,
+}
+
+#[get("/miners/{address}/history")]
+async fn history(
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<String>,
+    query: web::Query<HistoryRequest>,
+) -> impl Responder {
+    let miner_address = path.into_inner();
+    let limit = query.limit.unwrap_or(100);
+
+    let Ok(pkh) = address::pkh_from_address(&miner_address) else {
+        return HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: format!("Could not create a valid public key hash for address {}", miner_address),
+        });
+    };
+
+    let Ok(miner) = get_miner_by_pkh(&pool, &pkh).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch miner."),
+        });
+    };
+
+    // A miner's payouts follow them to any payout address, so they are looked up by who earned them.
+    let payouts = match miner {
+        Some(miner) => payout::get_by_miner(&pool, miner.id, limit).await,
+        None => payout::get_by_pkh(&pool, &pkh, limit).await,
+    };
+    let Ok(payouts) = payouts else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch payouts."),
+        });
+    };
+
+    let Ok(withdrawals) = withdrawal::get_by_pkh(&pool, &pkh, limit).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch withdrawals."),
+        });
+    };
+
+    HttpResponse::Ok().json(HistoryResponse {
+        address: miner_address,
+        pkh,
+        payouts,
+        withdrawals,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct PayoutAddressRequest {
+    payout_address: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PayoutAddressResponse {
+    address: String,
+    payout_address: String,
+    next_change_number: i64,
+    /// The message to sign to change to the requested payout address.
+    message: Option<String>,
+    changes: Vec<PayoutAddressChange>,
+}
+
+#[get("/miners/{address}/payout-address")]
+async fn payout_address(
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<String>,
+    query: web::Query<PayoutAddressRequest>,
+) -> impl Responder {
+    let miner_address = path.into_inner();
+
+    let Ok(pkh) = address::pkh_from_address(&miner_address) else {
+        return HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: format!("Could not create a valid public key hash for address {}", miner_address),
+        });
+    };
+
+    let miner = match get_miner_by_pkh(&pool, &pkh).await {
+        Ok(Some(miner)) => miner,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(GenericMessageResponse {
+                message: format!("No miner found for address {}", miner_address),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to retrieve miner."),
+            });
+        }
+    };
+
+    let Ok(next_change_number) = next_change_number(&pool, miner.id).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch payout address changes."),
+        });
+    };
+
+    let Ok(changes) = get_changes(&pool, miner.id).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch payout address changes."),
+        });
+    };
+
+    HttpResponse::Ok().json(PayoutAddressResponse {
+        payout_address: miner.payout_address().to_string(),
+        message: query.payout_address.as_ref()
+            .map(|payout_address| payout_address_message(&miner_address, payout_address, next_change_number)),
+        address: miner_address,
+        next_change_number,
+        changes,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangePayoutAddressRequest {
+    payout_address: String,
+    signature: String,
+    key: String,
+}
+
+#[post("/miners/{address}/payout-address")]
+async fn set_payout_address(
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<String>,
+    body: web::Json<ChangePayoutAddressRequest>,
+) -> impl Responder {
+    let miner_address = path.into_inner();
+
+    match change_payout_address(&pool, &miner_address, &body.payout_address, &body.signature, &body.key).await {
+        Ok(()) => HttpResponse::Ok().json(GenericMessageResponse {
+            message: format!("Rewards for {} will be credited to {}", miner_address, body.payout_address),
+        }),
+        Err(PayoutAddressError::UnknownMiner) => HttpResponse::NotFound().json(GenericMessageResponse {
+            message: format!("No miner found for address {}", miner_address),
+        }),
+        Err(PayoutAddressError::AddressParseError(e)) => HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: e.0,
+        }),
+        Err(PayoutAddressError::SignatureError(e)) => HttpResponse::Unauthorized().json(GenericMessageResponse {
+            message: format!("Signature does not authorize this change: {:?}", e),
+        }),
+        Err(PayoutAddressError::DatabaseError(e)) => {
+            log::error!("Failed to change payout address for {}: {:?}", miner_address, e);
+            HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to change payout address."),
+            })
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PayoutSplitsRequest {
+    splits: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PayoutSplitsResponse {
+    address: String,
+    splits: Vec<PayoutSplit>,
+    next_change_number: i64,
+    /// The message to sign to change to the requested splits.
+    message: Option<String>,
+    changes: Vec<PayoutSplitChange>,
+}
+
+#[get("/miners/{address}/payout-splits")]
+async fn payout_splits(
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<String>,
+    query: web::Query<PayoutSplitsRequest>,
+) -> impl Responder {
+    let miner_address = path.into_inner();
+
+    let Ok(pkh) = address::pkh_from_address(&miner_address) else {
+        return HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: format!("Could not create a valid public key hash for address {}", miner_address),
+        });
+    };
+
+    let miner = match get_miner_by_pkh(&pool, &pkh).await {
+        Ok(Some(miner)) => miner,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(GenericMessageResponse {
+                message: format!("No miner found for address {}", miner_address),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to retrieve miner."),
+            });
+        }
+    };
+
+    let requested_splits = match query.splits.as_deref().map(parse_splits).transpose() {
+        Ok(requested_splits) => requested_splits,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(GenericMessageResponse {
+                message: format!("Invalid splits: {:?}", e),
+            });
+        }
+    };
+
+    let Ok(next_change_number) = next_split_change_number(&pool, miner.id).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch payout split changes."),
+        });
+    };
+
+    let Ok(splits) = payout_split::get_by_miner(&pool, miner.id).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch payout splits."),
+        });
+    };
+
+    let Ok(changes) = payout_split::get_changes(&pool, miner.id).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch payout split changes."),
+        });
+    };
+
+    HttpResponse::Ok().json(PayoutSplitsResponse {
+        message: requested_splits
+            .map(|requested_splits| payout_split_message(&miner_address, &requested_splits, next_change_number)),
+        address: miner_address,
+        splits,
+        next_change_number,
+        changes,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct PayoutSplitEntry {
+    address: String,
+    basis_points: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangePayoutSplitsRequest {
+    splits: Vec<PayoutSplitEntry>,
+    signature: String,
+    key: String,
+}
+
+#[post("/miners/{address}/payout-splits")]
+async fn set_payout_splits(
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<String>,
+    body: web::Json<ChangePayoutSplitsRequest>,
+) -> impl Responder {
+    let miner_address = path.into_inner();
+    let splits: Vec<NewPayoutSplit> = body.splits.iter()
+        .map(|split| NewPayoutSplit { address: split.address.clone(), basis_points: split.basis_points })
+        .collect();
+
+    match change_payout_splits(&pool, &miner_address, &splits, &body.signature, &body.key).await {
+        Ok(()) if splits.is_empty() => HttpResponse::Ok().json(GenericMessageResponse {
+            message: format!("Rewards for {} will no longer be split", miner_address),
+        }),
+        Ok(()) => HttpResponse::Ok().json(GenericMessageResponse {
+            message: format!("Rewards for {} will be split between {} addresses", miner_address, splits.len()),
+        }),
+        Err(PayoutSplitError::UnknownMiner) => HttpResponse::NotFound().json(GenericMessageResponse {
+            message: format!("No miner found for address {}", miner_address),
+        }),
+        Err(PayoutSplitError::AddressParseError(e)) => HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: e.0,
+        }),
+        Err(PayoutSplitError::InvalidSplits(message)) => HttpResponse::BadRequest().json(GenericMessageResponse {
+            message,
+        }),
+        Err(PayoutSplitError::SignatureError(e)) => HttpResponse::Unauthorized().json(GenericMessageResponse {
+            message: format!("Signature does not authorize this change: {:?}", e),
+        }),
+        Err(PayoutSplitError::DatabaseError(e)) => {
+            log::error!("Failed to change payout splits for {}: {:?}", miner_address, e);
+            HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to change payout splits."),
+            })
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SoloModeRequest {
+    solo: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+struct SoloModeResponse {
+    address: String,
+    solo: bool,
+    /// The mode the miner switches to when the next block is seen, if they asked for one.
+    pending_solo: Option<bool>,
+    next_change_number: i64,
+    /// The message to sign to switch to the requested mode.
+    message: Option<String>,
+    changes: Vec<SoloModeChange>,
+}
+
+#[get("/miners/{address}/solo-mode")]
+async fn solo_mode(
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<String>,
+    query: web::Query<SoloModeRequest>,
+) -> impl Responder {
+    let miner_address = path.into_inner();
+
+    let Ok(pkh) = address::pkh_from_address(&miner_address) else {
+        return HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: format!("Could not create a valid public key hash for address {}", miner_address),
+        });
+    };
+
+    let miner = match get_miner_by_pkh(&pool, &pkh).await {
+        Ok(Some(miner)) => miner,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(GenericMessageResponse {
+                message: format!("No miner found for address {}", miner_address),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to retrieve miner."),
+            });
+        }
+    };
+
+    let Ok(next_change_number) = next_solo_change_number(&pool, miner.id).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch solo mode changes."),
+        });
+    };
+
+    let Ok(pending_solo) = get_pending_solo_mode(&pool, miner.id).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch solo mode changes."),
+        });
+    };
+
+    let Ok(changes) = get_solo_mode_changes(&pool, miner.id).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch solo mode changes."),
+        });
+    };
+
+    HttpResponse::Ok().json(SoloModeResponse {
+        message: query.solo.map(|solo| solo_mode_message(&miner_address, solo, next_change_number)),
+        address: miner_address,
+        solo: miner.solo,
+        pending_solo,
+        next_change_number,
+        changes,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangeSoloModeRequest {
+    solo: bool,
+    signature: String,
+    key: String,
+}
+
+#[post("/miners/{address}/solo-mode")]
+async fn set_solo_mode(
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<String>,
+    body: web::Json<ChangeSoloModeRequest>,
+) -> impl Responder {
+    let miner_address = path.into_inner();
+    let mode = if body.solo { "solo" } else { "in the pool" };
+
+    match change_solo_mode(&pool, &miner_address, body.solo, &body.signature, &body.key).await {
+        Ok(()) => HttpResponse::Ok().json(GenericMessageResponse {
+            message: format!("{} will mine {} from the next block", miner_address, mode),
+        }),
+        Err(SoloModeError::UnknownMiner) => HttpResponse::NotFound().json(GenericMessageResponse {
+            message: format!("No miner found for address {}", miner_address),
+        }),
+        Err(SoloModeError::AddressParseError(e)) => HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: e.0,
+        }),
+        Err(SoloModeError::SignatureError(e)) => HttpResponse::Unauthorized().json(GenericMessageResponse {
+            message: format!("Signature does not authorize this change: {:?}", e),
+        }),
+        Err(SoloModeError::DatabaseError(e)) => {
+            log::error!("Failed to change solo mode for {}: {:?}", miner_address, e);
+            HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to change solo mode."),
+            })
+        }
+    }
+}
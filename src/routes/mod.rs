@@ -1,3 +1,11 @@
 pub mod submit;
 pub mod hashrate;
-pub mod work;
\ No newline at end of file
+pub mod work;
+pub mod round;
+pub mod balance;
+pub mod payout;
+pub mod admin;
+pub mod metrics;
+pub mod miner;
+pub mod pool;
+pub mod block;
\ No newline at end of file
@@ -0,0 +1,219 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    address::pkh_from_address,
+    common::GenericMessageResponse,
+    model::{miner::{get_miner_by_pkh, get_payout_addresses}, proof_of_work::count_solo_since, round::get_open},
+    routes::hashrate::estimate_hashes_for_difficulty,
+    service::{
+        fee::load_fee_schedule,
+        payout::{calculate_solo_payout, split_payouts, Finder},
+        payout_split::TOTAL_BASIS_POINTS,
+        reward::RewardScheme,
+        submission::{load_payout_splits, plan_round_payouts},
+    },
+};
+
+#[derive(Debug, Deserialize)]
+struct PayoutPreviewRequest {
+    address: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PayoutPreviewResponse {
+    round_id: i32,
+    round_started_at: NaiveDateTime,
+    scheme: String,
+    total_estimated_hashes: f64,
+    pool_fee: u64,
+    finders_fee: u64,
+    miners: Vec<MinerPayoutPreview>,
+    solo_miners: Vec<SoloMinerPayoutPreview>,
+}
+
+#[derive(Debug, Serialize)]
+struct MinerPayoutPreview {
+    miner_id: i32,
+    address: String,
+    share_count: i64,
+    estimated_hashes: f64,
+    share_percentage: f64,
+    split_basis_points: Option<i32>,
+    pool_fee: u64,
+    fee_policy: String,
+    expected_amount: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct SoloMinerPayoutPreview {
+    miner_id: i32,
+    address: String,
+    share_count: i64,
+    estimated_hashes: f64,
+    split_basis_points: Option<i32>,
+    pool_fee: u64,
+    fee_policy: String,
+    expected_amount: u64,
+}
+
+// What each miner would be paid if the open round's block were found right now, by nobody in particular.
+#[get("/payouts/preview")]
+async fn preview(
+    pool: web::Data<Pool<Postgres>>,
+    query: web::Query<PayoutPreviewRequest>,
+) -> impl Responder {
+    let round = match get_open(&pool).await {
+        Ok(Some(round)) => round,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(GenericMessageResponse {
+                message: String::from("No round is open."),
+            });
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to fetch the open round."),
+            });
+        }
+    };
+
+    let (miner_weights, payout_plan) = match plan_round_payouts(&pool, round.id, Utc::now().naive_utc(), None).await {
+        Ok(plan) => plan,
+        Err(e) => {
+            log::error!("Failed to preview payouts for round {}: {}", round.id, e);
+            return HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to calculate payouts."),
+            });
+        }
+    };
+
+    // A miner's payouts follow them to any payout address, so a miner's address matches by who earned them.
+    let miner = match query.address.as_ref().and_then(|address| pkh_from_address(address).ok()) {
+        Some(pkh) => get_miner_by_pkh(&pool, &pkh).await,
+        None => Ok(None),
+    };
+    let Ok(miner) = miner else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch miner."),
+        });
+    };
+
+    let total_weight: f64 = miner_weights.iter().map(|w| w.weight).sum();
+    let total_estimated_hashes: f64 = miner_weights.iter().map(|w| w.estimated_hashes).sum();
+
+    let miners: Vec<MinerPayoutPreview> = payout_plan.payouts
+        .into_iter()
+        .filter(|payout| match (&query.address, &miner) {
+            (None, _) => true,
+            (Some(_), Some(miner)) => payout.miner_id == miner.id,
+            (Some(address), None) => *address == payout.address,
+        })
+        .map(|payout| {
+            let weight = miner_weights.iter()
+                .find(|w| w.miner_id == payout.miner_id)
+                .map(|w| w.weight)
+                .unwrap_or(0.0);
+            let split = payout.split_basis_points.unwrap_or(TOTAL_BASIS_POINTS) as f64 / TOTAL_BASIS_POINTS as f64;
+
+            MinerPayoutPreview {
+                miner_id: payout.miner_id,
+                address: payout.address,
+                share_count: payout.share_count,
+                estimated_hashes: payout.estimated_hashes,
+                share_percentage: if total_weight > 0.0 { weight * split / total_weight * 100.0 } else { 0.0 },
+                split_basis_points: payout.split_basis_points,
+                pool_fee: payout.pool_fee,
+                fee_policy: payout.fee_policy,
+                expected_amount: payout.amount,
+            }
+        })
+        .collect();
+
+    let Ok(solo_counts) = count_solo_since(&pool, round.started_at).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch solo shares."),
+        });
+    };
+
+    let Ok(fee_schedule) = load_fee_schedule(&pool).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to load the fee schedule."),
+        });
+    };
+
+    let Ok(payout_addresses) = get_payout_addresses(&pool).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch payout addresses."),
+        });
+    };
+
+    let Ok(payout_splits) = load_payout_splits(&pool).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch payout splits."),
+        });
+    };
+
+    // Solo shares are counted per difficulty, so a miner's counts are added up before their reward is planned.
+    let mut solo_totals: Vec<(Finder, i64, f64)> = Vec::new();
+    for solo_count in solo_counts {
+        let estimated_hashes = estimate_hashes_for_difficulty(solo_count.proof_count as usize, solo_count.sampling_difficulty as u8);
+
+        match solo_totals.iter_mut().find(|(finder, _, _)| finder.miner_id == solo_count.miner_id) {
+            Some((_, share_count, total_hashes)) => {
+                *share_count += solo_count.proof_count;
+                *total_hashes += estimated_hashes;
+            }
+            None => {
+                let address = payout_addresses.get(&solo_count.miner_id).cloned().unwrap_or(solo_count.miner_address);
+                solo_totals.push((Finder { miner_id: solo_count.miner_id, address }, solo_count.proof_count, estimated_hashes));
+            }
+        }
+    }
+
+    // A solo block pays its finder the same way `submit_solo` does: to their payout address, then split.
+    let mut solo_miners: Vec<SoloMinerPayoutPreview> = Vec::new();
+    for (finder, share_count, estimated_hashes) in solo_totals {
+        let Ok(mut solo_plan) = calculate_solo_payout(&fee_schedule, &finder) else {
+            return HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to calculate payouts."),
+            });
+        };
+        for solo_payout in solo_plan.payouts.iter_mut() {
+            solo_payout.share_count = share_count;
+            solo_payout.estimated_hashes = estimated_hashes;
+        }
+
+        let solo_plan = split_payouts(solo_plan, &payout_splits);
+        solo_miners.extend(solo_plan.payouts
+            .into_iter()
+            .filter(|payout| match (&query.address, &miner) {
+                (None, _) => true,
+                (Some(_), Some(miner)) => payout.miner_id == miner.id,
+                (Some(address), None) => *address == payout.address,
+            })
+            .map(|payout| SoloMinerPayoutPreview {
+                miner_id: payout.miner_id,
+                address: payout.address,
+                share_count: payout.share_count,
+                estimated_hashes: payout.estimated_hashes,
+                split_basis_points: payout.split_basis_points,
+                pool_fee: payout.pool_fee,
+                fee_policy: payout.fee_policy,
+                expected_amount: payout.amount,
+            }));
+    }
+    solo_miners.sort_by_key(|solo_miner| solo_miner.miner_id);
+
+    HttpResponse::Ok().json(PayoutPreviewResponse {
+        round_id: round.id,
+        round_started_at: round.started_at,
+        scheme: RewardScheme::from_env().name().to_string(),
+        total_estimated_hashes,
+        pool_fee: payout_plan.pool_fee,
+        finders_fee: payout_plan.unassigned,
+        miners,
+        solo_miners,
+    })
+}
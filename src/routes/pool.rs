@@ -0,0 +1,250 @@
+use std::sync::Arc;
+
+use actix_web::{get, web, HttpResponse, Responder};
+use chrono::{Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    common::GenericMessageResponse,
+    model::{
+        datum_submission::{count_by_status, get_efforts, DatumCounts, DatumEffort, DatumStatus},
+        proof_of_work::{count_active, count_by_round, count_by_time_range},
+        round::get_open,
+    },
+    routes::hashrate::{estimate_hashes_for_difficulty, estimate_hashrate, expected_hashes_for_target},
+    service::{
+        block::{BlockService, ReadableBlock},
+        network::{get_network_stats, NetworkStats},
+    },
+};
+
+/// Hash rate and active miners are measured over the last 10 minutes.
+const POOL_STATS_WINDOW_SECONDS: i64 = 600;
+
+const DEFAULT_LUCK_LIMIT: i64 = 50;
+const MAX_LUCK_LIMIT: i64 = 500;
+const DEFAULT_LUCK_WINDOW: i64 = 10;
+
+#[derive(Debug, Serialize)]
+struct PoolStatsResponse {
+    hash_rate_window_seconds: i64,
+    estimated_hash_rate: f64,
+    active_miners: i64,
+    active_workers: i64,
+    current_block: Option<ReadableBlock>,
+    blocks: BlockCounts,
+    last_confirmed_at: Option<NaiveDateTime>,
+    seconds_since_last_confirmed: Option<i64>,
+    /// Null if no round is open.
+    round: Option<RoundEffort>,
+    /// Null until the pool has seen two blocks in a row.
+    network: Option<NetworkStats>,
+}
+
+#[derive(Debug, Serialize)]
+struct BlockCounts {
+    found: i64,
+    pending: i64,
+    confirmed: i64,
+    rejected: i64,
+    orphaned: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct RoundEffort {
+    id: i32,
+    started_at: NaiveDateTime,
+    estimated_hashes: f64,
+    /// The hashes the network expects it to take to find the current block.
+    expected_hashes: Option<f64>,
+    /// `estimated_hashes` over `expected_hashes`. Below 1 is a lucky round so far, above 1 an unlucky one.
+    effort: Option<f64>,
+}
+
+#[get("/pool/stats")]
+async fn stats(
+    pool: web::Data<Pool<Postgres>>,
+    block_service: web::Data<Arc<BlockService>>,
+) -> impl Responder {
+    let now = Utc::now().naive_utc();
+    let start_time = now - Duration::seconds(POOL_STATS_WINDOW_SECONDS);
+
+    let Ok(proof_counts) = count_by_time_range(&pool, None, start_time, now).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch proofs of work."),
+        });
+    };
+
+    let Ok(active) = count_active(&pool, start_time).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to count active miners."),
+        });
+    };
+
+    let Ok(DatumCounts { found, pending, confirmed, rejected, orphaned, last_confirmed_at }) = count_by_status(&pool).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to count blocks found."),
+        });
+    };
+
+    let Ok(round) = get_open(&pool).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch the open round."),
+        });
+    };
+
+    let round_counts = match &round {
+        Some(round) => count_by_round(&pool, round.id).await,
+        None => Ok(Vec::new()),
+    };
+    let Ok(round_counts) = round_counts else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch the open round's shares."),
+        });
+    };
+
+    let round_hashes: f64 = round_counts.iter().fold(0.0, |hashes, proof_count| {
+        hashes + estimate_hashes_for_difficulty(proof_count.proof_count as usize, proof_count.sampling_difficulty as u8)
+    });
+
+    let estimated_hash_rate = estimate_hashrate(proof_counts, start_time, now);
+
+    let network = match get_network_stats(&pool, estimated_hash_rate).await {
+        Ok(network) => network,
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(GenericMessageResponse {
+                message: String::from("Failed to fetch recent blocks."),
+            });
+        }
+    };
+
+    // The stats are still worth having while the block service is catching up, which it reports as block 0.
+    let current_block = block_service.get_latest().ok().filter(|block| block.block_number > 0);
+    let expected_hashes = current_block.as_ref()
+        .map(|block| expected_hashes_for_target(block.leading_zeroes, block.difficulty_number));
+
+    HttpResponse::Ok().json(PoolStatsResponse {
+        hash_rate_window_seconds: POOL_STATS_WINDOW_SECONDS,
+        estimated_hash_rate,
+        active_miners: active.miners,
+        active_workers: active.workers,
+        current_block: current_block.map(|block| block.into()),
+        blocks: BlockCounts {
+            found,
+            pending,
+            confirmed,
+            rejected,
+            orphaned,
+        },
+        last_confirmed_at,
+        seconds_since_last_confirmed: last_confirmed_at.map(|confirmed_at| (now - confirmed_at).num_seconds()),
+        round: round.map(|round| RoundEffort {
+            id: round.id,
+            started_at: round.started_at,
+            estimated_hashes: round_hashes,
+            expected_hashes,
+            effort: expected_hashes.map(|expected_hashes| round_hashes / expected_hashes),
+        }),
+        network,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct LuckRequest {
+    limit: Option<i64>,
+    window: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct LuckResponse {
+    window: i64,
+    blocks: usize,
+    /// Over every block listed, weighted by the hashes each was expected to take.
+    average_effort: Option<f64>,
+    average_luck: Option<f64>,
+    history: Vec<BlockLuck>,
+}
+
+#[derive(Debug, Serialize)]
+struct BlockLuck {
+    block_number: i32,
+    transaction_hash: String,
+    created_at: NaiveDateTime,
+    status: DatumStatus,
+    estimated_hashes: f64,
+    expected_hashes: f64,
+    effort: Option<f64>,
+    luck: Option<f64>,
+    /// Over this block and up to `window - 1` before it.
+    rolling_effort: Option<f64>,
+    rolling_luck: Option<f64>,
+}
+
+// Effort is the hashes put in over the hashes expected, and luck its inverse. Either is None when what it
+// divides by is zero, as for a block found before the pool recorded any shares.
+fn effort_and_luck(efforts: &[DatumEffort]) -> (Option<f64>, Option<f64>) {
+    let (estimated_hashes, expected_hashes) = efforts.iter().fold((0.0, 0.0), |(estimated, expected), effort| {
+        (estimated + effort.estimated_hashes, expected + effort.expected_hashes)
+    });
+
+    (
+        (expected_hashes > 0.0).then(|| estimated_hashes / expected_hashes),
+        (estimated_hashes > 0.0).then(|| expected_hashes / estimated_hashes),
+    )
+}
+
+#[get("/pool/luck")]
+async fn luck(
+    pool: web::Data<Pool<Postgres>>,
+    query: web::Query<LuckRequest>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(DEFAULT_LUCK_LIMIT);
+    let window = query.window.unwrap_or(DEFAULT_LUCK_WINDOW);
+    if !(1..=MAX_LUCK_LIMIT).contains(&limit) || !(1..=MAX_LUCK_LIMIT).contains(&window) {
+        return HttpResponse::BadRequest().json(GenericMessageResponse {
+            message: format!("limit and window must be between 1 and {}.", MAX_LUCK_LIMIT),
+        });
+    }
+
+    // The oldest blocks listed still need a full window behind them.
+    let Ok(mut efforts) = get_efforts(&pool, limit + window - 1).await else {
+        return HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch found blocks."),
+        });
+    };
+    efforts.reverse();
+
+    let listed = efforts.len().saturating_sub(limit as usize);
+    let (average_effort, average_luck) = effort_and_luck(&efforts[listed..]);
+
+    let mut history: Vec<BlockLuck> = Vec::with_capacity(efforts.len() - listed);
+    for index in (listed..efforts.len()).rev() {
+        let window_start = (index + 1).saturating_sub(window as usize);
+        let (effort, luck) = effort_and_luck(&efforts[index..=index]);
+        let (rolling_effort, rolling_luck) = effort_and_luck(&efforts[window_start..=index]);
+
+        let datum = &efforts[index];
+
+        history.push(BlockLuck {
+            block_number: datum.block_number,
+            transaction_hash: datum.transaction_hash.clone(),
+            created_at: datum.created_at,
+            status: DatumStatus::new(datum.rejected, datum.orphaned, datum.confirmed_in_slot.is_some()),
+            estimated_hashes: datum.estimated_hashes,
+            expected_hashes: datum.expected_hashes,
+            effort,
+            luck,
+            rolling_effort,
+            rolling_luck,
+        });
+    }
+
+    HttpResponse::Ok().json(LuckResponse {
+        window,
+        blocks: history.len(),
+        average_effort,
+        average_luck,
+        history,
+    })
+}
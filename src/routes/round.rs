@@ -0,0 +1,26 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+
+use crate::{common::GenericMessageResponse, model::round::get_by_share};
+
+#[derive(Debug, Deserialize)]
+struct RoundRequest {
+    sha: String,
+}
+
+#[get("/round")]
+async fn round(
+    pool: web::Data<Pool<Postgres>>,
+    query: web::Query<RoundRequest>,
+) -> impl Responder {
+    match get_by_share(&pool, &query.sha).await {
+        Ok(Some(round)) => HttpResponse::Ok().json(round),
+        Ok(None) => HttpResponse::NotFound().json(GenericMessageResponse {
+            message: format!("No round found for share {}", query.sha),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json(GenericMessageResponse {
+            message: String::from("Failed to fetch round."),
+        }),
+    }
+}
@@ -8,6 +8,7 @@ use tokio::sync::Mutex;
 use tokio::time::Instant;
 use crate::common::GenericMessageResponse;
 use crate::routes::work::generate_nonce;
+use crate::service::metrics::{RATE_LIMITED_SUBMISSIONS, SUBMIT_LATENCY};
 use crate::service::proof_of_work::{block_to_target_state, RawSubmitProofOfWorkResponse};
 use crate::{address, service::{proof_of_work::{submit_proof_of_work, SubmitProofOfWorkError}, block::BlockService}, model::miner::get_miner_by_pkh};
 
@@ -19,7 +20,8 @@ struct SubmissionQuery {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Submission {
     pub address: String,
-    pub entries: Vec<SubmissionEntry>
+    pub entries: Vec<SubmissionEntry>,
+    pub worker: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,6 +30,8 @@ pub struct SubmissionEntry {
 }
 
 
+const MAX_WORKER_NAME_LENGTH: usize = 64;
+
 lazy_static! {
     static ref RATE_LIMITER: Mutex<HashMap<String, (Instant, usize)>> = Mutex::new(HashMap::new());
 }
@@ -39,6 +43,19 @@ async fn submit(
     submission: web::Json<Submission>,
     query: web::Query<SubmissionQuery>,
 ) -> impl Responder {
+    let started_at = Instant::now();
+    let response = handle_submission(pool, block_service, submission, query).await;
+    SUBMIT_LATENCY.observe(started_at.elapsed());
+
+    response
+}
+
+async fn handle_submission(
+    pool: web::Data<Pool<Postgres>>,
+    block_service: web::Data<Arc<BlockService>>,
+    submission: web::Json<Submission>,
+    query: web::Query<SubmissionQuery>,
+) -> HttpResponse {
     let max_submissions_per_minute: usize = std::env::var("MAX_SUBMISSIONS_PER_MINUTE")
         .unwrap_or_else(|_| "500".to_string())
         .parse()
@@ -54,6 +71,14 @@ async fn submit(
         )
     };
 
+    if submission.worker.as_ref().is_some_and(|worker| worker.len() > MAX_WORKER_NAME_LENGTH) {
+        return HttpResponse::BadRequest().json(
+            GenericMessageResponse { 
+                message: format!("Worker names can be at most {} bytes.", MAX_WORKER_NAME_LENGTH)
+            }
+        )
+    }
+
     let mut rate_limiter = RATE_LIMITER.lock().await;
     let current_time = Instant::now();
     let new_entries_count = submission.entries.len();
@@ -65,6 +90,7 @@ async fn submit(
             if current_time.duration_since(*last_request_time) < tokio::time::Duration::from_secs(60) {
                 *count += new_entries_count;
                 if *count > max_submissions_per_minute {
+                    RATE_LIMITED_SUBMISSIONS.inc();
                     return HttpResponse::TooManyRequests().json(
                         GenericMessageResponse { 
                             message: String::from("Too many submissions in the past minute.")
@@ -98,7 +124,15 @@ async fn submit(
         )
     };
 
-    let result = submit_proof_of_work(&pool, &block_service, miner.id, miner.sampling_difficulty as u8, &submission).await;
+    if miner.banned {
+        return HttpResponse::Forbidden().json(
+            GenericMessageResponse { 
+                message: format!("Miner {} is banned.", submission.address)
+            }
+        )
+    }
+
+    let result = submit_proof_of_work(&pool, &block_service, miner.id, miner.sampling_difficulty as u8, miner.solo, &submission).await;
 
     match result {
         Ok(submission_response) => {
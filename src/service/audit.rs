@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use sqlx::{Pool, Postgres};
+
+use crate::model::{block, proof_of_work::{self, ProofOfWork}};
+
+use super::block::Block;
+use super::proof_of_work::{block_to_target_state, get_difficulty, sha256_digest_as_bytes, verify_nonce};
+
+#[derive(Debug)]
+pub enum AuditFailureReason {
+    MissingBlock,
+    MalformedNonce,
+    ShaMismatch { recomputed: String },
+    NonceNotAssigned,
+    InsufficientDifficulty { leading_zeroes: u128, required: i32 },
+}
+
+#[derive(Debug)]
+pub struct AuditFailure {
+    pub proof: ProofOfWork,
+    pub reason: AuditFailureReason,
+}
+
+#[derive(Debug, Default)]
+pub struct AuditReport {
+    pub checked: usize,
+    pub failures: Vec<AuditFailure>,
+}
+
+pub async fn audit_proofs(
+    pool: &Pool<Postgres>,
+    miner_id: Option<i32>,
+    from_block: i32,
+    to_block: i32,
+) -> Result<AuditReport, sqlx::Error> {
+    let pool_id: u8 = std::env::var("POOL_ID")
+        .expect("POOL_ID must be set")
+        .parse()
+        .expect("POOL_ID must be a valid number");
+
+    let blocks: HashMap<i32, Block> = block::get_by_block_range(pool, from_block, to_block)
+        .await?
+        .into_iter()
+        .map(|stored| (stored.block_number, stored.into()))
+        .collect();
+
+    let proofs = proof_of_work::get_by_block_range(pool, miner_id, from_block, to_block).await?;
+
+    let mut report = AuditReport::default();
+    for proof in proofs {
+        report.checked += 1;
+
+        let verification = match blocks.get(&proof.block_number) {
+            Some(block) => verify_proof(block, &proof, pool_id),
+            None => Err(AuditFailureReason::MissingBlock),
+        };
+
+        if let Err(reason) = verification {
+            report.failures.push(AuditFailure { proof, reason });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Re-derives the sha of a stored proof from the block it was mined against and checks that
+/// it still satisfies everything `submit_proof_of_work` checked when it was accepted.
+pub fn verify_proof(block: &Block, proof: &ProofOfWork, pool_id: u8) -> Result<(), AuditFailureReason> {
+    let nonce: [u8; 16] = hex::decode(&proof.nonce)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(AuditFailureReason::MalformedNonce)?;
+
+    let target_state_bytes = block_to_target_state(block, &nonce).to_bytes();
+    let hashed_data = sha256_digest_as_bytes(&target_state_bytes);
+    let hashed_hash = sha256_digest_as_bytes(&hashed_data);
+
+    let recomputed = hex::encode(hashed_hash);
+    if recomputed != proof.sha {
+        return Err(AuditFailureReason::ShaMismatch { recomputed });
+    }
+
+    if !verify_nonce(&nonce, proof.miner_id, pool_id) {
+        return Err(AuditFailureReason::NonceNotAssigned);
+    }
+
+    let difficulty = get_difficulty(&hashed_hash);
+    if difficulty.leading_zeroes < proof.sampling_difficulty as u128 {
+        return Err(AuditFailureReason::InsufficientDifficulty {
+            leading_zeroes: difficulty.leading_zeroes,
+            required: proof.sampling_difficulty,
+        });
+    }
+
+    Ok(())
+}
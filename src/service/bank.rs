@@ -0,0 +1,213 @@
+use std::{collections::HashMap, fmt};
+
+use cardano_multiplatform_lib::plutus::PlutusData;
+use serde::Deserialize;
+
+use super::block::{kupo_get, KupoUtxo};
+
+const BANK_ASSET_NAME: &str = "42414e4b"; // "BANK"
+
+/// The pool's BANK UTxO. `owners` maps each payment key hash to the TUNA banked for it.
+#[derive(Debug, Clone)]
+pub struct Bank {
+    pub owners: HashMap<String, u64>,
+    pub transaction_id: String,
+    pub slot_no: i32,
+}
+
+#[derive(Debug)]
+pub enum BankError {
+    ReqwestError(reqwest::Error),
+    NoBankUtxo,
+    MissingDatum,
+    DatumParseFailure,
+}
+
+impl fmt::Display for BankError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BankError::ReqwestError(e) => write!(f, "could not reach kupo: {}", e),
+            BankError::NoBankUtxo => write!(f, "no BANK UTxO at the pool contract address"),
+            BankError::MissingDatum => write!(f, "the BANK UTxO has no datum"),
+            BankError::DatumParseFailure => write!(f, "the BANK datum could not be parsed"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for BankError {
+    fn from(err: reqwest::Error) -> Self {
+        BankError::ReqwestError(err)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct KupoDatumResponse {
+    datum: String
+}
+
+pub async fn get_current_bank() -> Result<Bank, BankError> {
+    let kupo_url = std::env::var("KUPO_URL").expect("KUPO_URL must be set");
+    let pool_contract_address = std::env::var("POOL_CONTRACT_ADDRESS").expect("POOL_CONTRACT_ADDRESS must be set");
+
+    let pool_contract_unspent_tx: Vec<KupoUtxo> = kupo_get(
+            format!("{}/matches/{}?unspent", kupo_url, pool_contract_address)
+        )
+        .await?;
+
+    let bank_utxo = pool_contract_unspent_tx.into_iter()
+        .find(is_bank_utxo)
+        .ok_or(BankError::NoBankUtxo)?;
+
+    bank_from_utxo(&kupo_url, bank_utxo).await
+}
+
+// The BANK UTxOs the pool contract created or spent after `after_slot`, or its whole history when there
+// is no bound. A bank created after the bound is always returned alongside the bank it spent, since that
+// one was spent in the same slot.
+pub async fn get_bank_history(kupo_url: &str, after_slot: Option<i64>) -> Result<Vec<KupoUtxo>, BankError> {
+    let pool_contract_address = std::env::var("POOL_CONTRACT_ADDRESS").expect("POOL_CONTRACT_ADDRESS must be set");
+    let pool_script_hash = std::env::var("POOL_SCRIPT_HASH").expect("POOL_SCRIPT_HASH must be set");
+
+    let matches_url = format!(
+        "{}/matches/{}?policy_id={}&asset_name={}",
+        kupo_url, pool_contract_address, pool_script_hash, BANK_ASSET_NAME
+    );
+
+    let mut bank_utxos: Vec<KupoUtxo> = match after_slot {
+        None => kupo_get(matches_url).await?,
+        Some(after_slot) => {
+            let mut created: Vec<KupoUtxo> = kupo_get(format!("{}&created_after={}", matches_url, after_slot)).await?;
+            let spent: Vec<KupoUtxo> = kupo_get(format!("{}&spent_after={}", matches_url, after_slot)).await?;
+
+            for utxo in spent {
+                let seen = created.iter().any(|created_utxo| {
+                    created_utxo.transaction_id == utxo.transaction_id && created_utxo.output_index == utxo.output_index
+                });
+
+                if !seen {
+                    created.push(utxo);
+                }
+            }
+
+            created
+        }
+    };
+
+    bank_utxos.retain(is_bank_utxo);
+
+    Ok(bank_utxos)
+}
+
+// The BANK UTxO that `utxo`'s transaction spent. Kupo only gives the slot a UTxO was spent in, and the
+// bank is spent at most once per slot, so that is enough to link the chain.
+pub fn previous_bank_utxo<'a>(bank_utxos: &'a [KupoUtxo], utxo: &KupoUtxo) -> Option<&'a KupoUtxo> {
+    bank_utxos.iter().find(|bank_utxo| {
+        bank_utxo.transaction_id != utxo.transaction_id &&
+        bank_utxo.spent_at.as_ref().map(|spent_at| spent_at.slot_no) == Some(utxo.created_at.slot_no)
+    })
+}
+
+pub fn is_bank_utxo(utxo: &KupoUtxo) -> bool {
+    let pool_script_hash = std::env::var("POOL_SCRIPT_HASH").expect("POOL_SCRIPT_HASH must be set");
+    let bank_token = format!("{}.{}", pool_script_hash, BANK_ASSET_NAME);
+
+    let Some(assets) = &utxo.value.assets else {
+        return false
+    };
+
+    assets.get(&bank_token) == Some(&1)
+}
+
+pub async fn bank_from_utxo(kupo_url: &str, utxo: KupoUtxo) -> Result<Bank, BankError> {
+    let datum_hash = utxo.datum_hash.as_ref().ok_or(BankError::MissingDatum)?;
+    let bank_datum: KupoDatumResponse = kupo_get(format!("{}/datums/{}", kupo_url, datum_hash)).await?;
+
+    Ok(Bank {
+        owners: owners_from_datum(&bank_datum.datum)?,
+        transaction_id: utxo.transaction_id,
+        slot_no: utxo.created_at.slot_no,
+    })
+}
+
+// The bank datum is `Constr 0 [Map<ByteArray, Int>]`, as written by the submission server's BankSchema.
+fn owners_from_datum(datum: &str) -> Result<HashMap<String, u64>, BankError> {
+    let hex_bytes = hex::decode(datum.as_bytes())
+        .map_err(|_| {
+            log::warn!("Could not decode hex from bank datum {}.", datum);
+            BankError::DatumParseFailure
+        })?;
+
+    let data = PlutusData::from_bytes(hex_bytes)
+        .map_err(|_| {
+            log::warn!("Could not create plutus data from bank datum bytes. {}.", datum);
+            BankError::DatumParseFailure
+        })?;
+
+    let typed_data = data
+        .as_constr_plutus_data()
+        .ok_or_else(|| {
+            log::warn!("Could not create constr plutus data for bank from parsed plutus data. {}.", datum);
+            BankError::DatumParseFailure
+        })?;
+
+    if typed_data.data().len() != 1 {
+        log::warn!("Bank datum should have exactly one field. {}.", datum);
+        return Err(BankError::DatumParseFailure);
+    }
+
+    let owners_map = typed_data.data().get(0).as_map().ok_or(BankError::DatumParseFailure)?;
+    let owner_keys = owners_map.keys();
+
+    let mut owners: HashMap<String, u64> = HashMap::new();
+    for i in 0..owner_keys.len() {
+        let key = owner_keys.get(i);
+        let pkh = key.as_bytes().ok_or(BankError::DatumParseFailure)?;
+        let amount: u64 = owners_map
+            .get(&key)
+            .and_then(|value| value.as_integer())
+            .and_then(|value| value.as_u64())
+            .ok_or(BankError::DatumParseFailure)?
+            .into();
+
+        owners.insert(hex::encode(pkh), amount);
+    }
+
+    Ok(owners)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Constr 0 [{ h'29a4..66ab': 100000000, h'8d18..b5f0': 5000000000 }], encoded the way lucid's Data.to writes BankData.
+    const BANK_DATUM: &str = concat!(
+        "d8799fa2",
+        "581c29a4f5c7c065ceaa9fd0ba46171616654cdc93bd51856588fe8766ab",
+        "1a05f5e100",
+        "581c8d18cfa9e4a4ab8f2d6a64d6b4c8e0e8a2a4c6f3e1d9b7a5c3e1b5f0",
+        "1b000000012a05f200",
+        "ff"
+    );
+
+    #[test]
+    fn owners_from_bank_datum() {
+        let owners = owners_from_datum(BANK_DATUM).unwrap();
+
+        assert_eq!(owners.len(), 2);
+        assert_eq!(owners.get("29a4f5c7c065ceaa9fd0ba46171616654cdc93bd51856588fe8766ab"), Some(&100_000_000));
+        assert_eq!(owners.get("8d18cfa9e4a4ab8f2d6a64d6b4c8e0e8a2a4c6f3e1d9b7a5c3e1b5f0"), Some(&5_000_000_000));
+    }
+
+    #[test]
+    fn owners_from_empty_bank_datum() {
+        assert!(owners_from_datum("d8799fa0ff").unwrap().is_empty());
+    }
+
+    #[test]
+    fn owners_from_malformed_datum() {
+        // not hex, an integer rather than a constr, and a constr with an empty list instead of the map
+        assert!(owners_from_datum("zz").is_err());
+        assert!(owners_from_datum("1a05f5e100").is_err());
+        assert!(owners_from_datum("d8799f80ff").is_err());
+    }
+}
@@ -1,8 +1,15 @@
 use std::collections::{VecDeque, HashMap};
 use std::sync::{Arc, RwLock};
+use std::time::Instant;
 use std::env;
 use cardano_multiplatform_lib::plutus::{PlutusData};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::{Postgres, Pool};
+
+use crate::model::{block::{self, StoredBlock}, solo_mode};
+
+use super::metrics::KUPO_REQUEST_ERRORS;
+use super::webhook::{notify, WebhookEvent};
 
 const MAX_ITEMS: usize = 10;  // For example
 const TUNA_CONTRACT_NFT_POLICY_MAINNET: &str = "279f842c33eed9054b9e3c70cd6a3b32298259c24b78b895cb41d91a.6c6f72642074756e61";
@@ -62,6 +69,7 @@ pub struct KupoUtxo {
     pub output_index: i64,
     pub transaction_id: String,
     pub created_at: KupoUtxoCreated,
+    pub spent_at: Option<KupoUtxoCreated>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -92,20 +100,21 @@ impl From<reqwest::Error> for BlockServiceError {
 pub struct BlockService {
     history: Arc<RwLock<VecDeque<Block>>>,
     kupo_url: String,
-    contract_address: String
+    contract_address: String,
+    // When upstream was last fetched successfully, whether or not it had a new block.
+    last_updated_at: RwLock<Option<Instant>>,
 }
 
 impl BlockService {
     pub fn new() -> Self {
         let history = Arc::new(RwLock::new(VecDeque::with_capacity(MAX_ITEMS)));
         let kupo_url = env::var("KUPO_URL").expect("Cannot instantiate BlockService because KUPO_URL is not set.");
-        let tuna_contract_address = env::var("TUNA_CONTRACT_ADDRESS")
-            .unwrap_or(String::from("addr1wynelppvx0hdjp2tnc78pnt28veznqjecf9h3wy4edqajxsg7hwsc"));
 
         BlockService { 
             history,
             kupo_url,
-            contract_address: tuna_contract_address
+            contract_address: tuna_contract_address(),
+            last_updated_at: RwLock::new(None),
         }
     }
 
@@ -118,41 +127,32 @@ impl BlockService {
         Ok(read_history.front().unwrap_or(&default_block).clone()) // We clone to own the data outside the lock
     }
 
+    pub fn seconds_since_update(&self) -> Option<f64> {
+        self.last_updated_at.read().ok()?.map(|updated_at| updated_at.elapsed().as_secs_f64())
+    }
 
-    async fn update_history(&self) -> Result<(), BlockServiceError> {
-        let network = std::env::var("NETWORK").unwrap_or(String::from("Mainnet"));
-        let nft_policy = match &*network {
-            "Preview" => TUNA_CONTRACT_NFT_POLICY_PREVIEW,
-            _ => TUNA_CONTRACT_NFT_POLICY_MAINNET
-        };
-
-        let all_contract_unspent_tx: Vec<KupoUtxo> = reqwest::get(
+    async fn update_history(&self) -> Result<Option<Block>, BlockServiceError> {
+        let all_contract_unspent_tx: Vec<KupoUtxo> = kupo_get(
                 format!("{}/matches/{}?unspent", self.kupo_url, self.contract_address)
             )
-            .await?
-            .json()
             .await?;
         
         let most_recent_datum_tx: KupoUtxo = all_contract_unspent_tx.into_iter()
-            .find(|tx| {
-                let Some(assets) = &tx.value.assets else {
-                    return false
-                };
-
-                assets.get(nft_policy) == Some(&1)
-            })
+            .find(is_block_utxo)
             .ok_or(BlockServiceError::NoMatchingContractTransaction)?;
 
         
         let datum_hash = &most_recent_datum_tx.clone().datum_hash.unwrap();
-        let most_recent_datum: KupoDatumResponse = reqwest::get(format!("{}/datums/{}", self.kupo_url, &datum_hash))
-            .await?
-            .json()
+        let most_recent_datum: KupoDatumResponse = kupo_get(format!("{}/datums/{}", self.kupo_url, &datum_hash))
             .await?;
         
         let default_block = Block::default();
         let most_recent_block = block_from_datum(most_recent_datum.datum, most_recent_datum_tx)?;
 
+        if let Ok(mut last_updated_at) = self.last_updated_at.write() {
+            *last_updated_at = Some(Instant::now());
+        }
+
         let last_seen_block = {
             let read_history = self.history.read().map_err(|_| {
                 log::warn!("Could not acquire read access to block service history. History was not updated.");
@@ -172,16 +172,83 @@ impl BlockService {
             }
             
             log::info!("Fetched new block {} from upstream and updated BlockService history.", &most_recent_block.block_number);
-            write_history.push_front(most_recent_block);
+            write_history.push_front(most_recent_block.clone());
+            Ok(Some(most_recent_block))
         } else {
             log::debug!("Successfully fetched from upstream, but no updates for BlockService found.");
+            Ok(None)
         }
+    }
+}
+
+fn tuna_contract_address() -> String {
+    env::var("TUNA_CONTRACT_ADDRESS")
+        .unwrap_or(String::from("addr1wynelppvx0hdjp2tnc78pnt28veznqjecf9h3wy4edqajxsg7hwsc"))
+}
+
+// Every block's datum sits in the one UTxO at the tuna contract holding its NFT.
+fn is_block_utxo(utxo: &KupoUtxo) -> bool {
+    let network = std::env::var("NETWORK").unwrap_or(String::from("Mainnet"));
+    let nft_policy = match &*network {
+        "Preview" => TUNA_CONTRACT_NFT_POLICY_PREVIEW,
+        _ => TUNA_CONTRACT_NFT_POLICY_MAINNET
+    };
+
+    let Some(assets) = &utxo.value.assets else {
+        return false
+    };
+
+    assets.get(nft_policy) == Some(&1)
+}
+
+/// The block mined on `block`, or None while `block` is still the chain's latest. Only the latest block is
+/// recorded on each poll, so this fills in one that was missed: the transaction that spent `block`'s UTxO
+/// is the one that created the next block's, in the same slot.
+pub async fn fetch_next_block(kupo_url: &str, block: &StoredBlock) -> Result<Option<Block>, BlockServiceError> {
+    let block_utxos: Vec<KupoUtxo> = kupo_get(
+            format!("{}/matches/{}@{}", kupo_url, block.output_index, block.transaction_id)
+        )
+        .await?;
+
+    let Some(spent_at) = block_utxos.into_iter().next().and_then(|utxo| utxo.spent_at) else {
+        return Ok(None);
+    };
+
+    let created_utxos: Vec<KupoUtxo> = kupo_get(
+            format!(
+                "{}/matches/{}?created_after={}&created_before={}",
+                kupo_url, tuna_contract_address(), spent_at.slot_no - 1, spent_at.slot_no + 1
+            )
+        )
+        .await?;
+
+    let next_utxo = created_utxos.into_iter()
+        .find(is_block_utxo)
+        .ok_or(BlockServiceError::NoMatchingContractTransaction)?;
 
-        Ok(())
+    let datum_hash = next_utxo.datum_hash.clone().ok_or(BlockServiceError::BlockParseFailure)?;
+    let datum: KupoDatumResponse = kupo_get(format!("{}/datums/{}", kupo_url, datum_hash)).await?;
+    let next_block = block_from_datum(datum.datum, next_utxo)?;
+
+    if next_block.block_number != block.block_number + 1 {
+        log::warn!("Expected block {} after block {}, but found block {}.", block.block_number + 1, block.block_number, next_block.block_number);
+        return Err(BlockServiceError::BlockParseFailure);
     }
+
+    Ok(Some(next_block))
 }
 
-pub async fn block_updater(service: Arc<BlockService>) {
+// Kupo requests go through here so their failures are counted in /metrics.
+pub async fn kupo_get<T: DeserializeOwned>(url: String) -> Result<T, reqwest::Error> {
+    let result = async { reqwest::get(url).await?.json().await }.await;
+    if result.is_err() {
+        KUPO_REQUEST_ERRORS.inc();
+    }
+
+    result
+}
+
+pub async fn block_updater(service: Arc<BlockService>, pool: Pool<Postgres>) {
     let default_interval = 20;
     let datum_update_interval: u64 = std::env::var("DATUM_UPDATE_INTERVAL")
         .unwrap_or_else(|_| default_interval.to_string())
@@ -191,7 +258,20 @@ pub async fn block_updater(service: Arc<BlockService>) {
     loop {
         let update = service.update_history().await;
         match update {
-            Ok(_) => {
+            Ok(Some(new_block)) => {
+                // switching between solo and pooled mining only ever takes effect on a new block
+                if let Err(err) = solo_mode::apply_pending(&pool).await {
+                    log::error!("Failed to apply solo mode changes at block {}: {:?}", new_block.block_number, err);
+                }
+
+                // keep a record of every block so stored proofs can be audited after the fact
+                if let Err(err) = block::create(&pool, &new_block).await {
+                    log::error!("Failed to persist block {}: {:?}", new_block.block_number, err);
+                }
+
+                notify(&pool, WebhookEvent::BlockSeen, &ReadableBlock::from(new_block)).await;
+            },
+            Ok(None) => {
                 // good
             },
             Err(err) => {
@@ -0,0 +1,239 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use chrono::{Duration, Utc};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    model::{fee_override::get_fee_overrides, proof_of_work::count_by_time_range},
+    routes::hashrate::estimate_hashes_for_difficulty,
+};
+
+/// Miners are placed in a fee tier by their hash rate over this many seconds.
+const TIER_HASH_RATE_WINDOW_SECONDS: i64 = 3600;
+
+/// How much of a miner's part of the reward the pool keeps.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeePolicy {
+    /// A fixed amount per datum, shared between miners by their part of the reward. `fixed:25000000`
+    Fixed { amount: u64 },
+    /// A percentage of the miner's reward. `percent:0.5`
+    Percent { basis_points: u64 },
+    /// A percentage of the miner's reward, set by the highest tier their hash rate reaches.
+    /// Miners below every tier pay the lowest one. `tiered:0=1,1000000000=0.5`
+    Tiered { tiers: Vec<FeeTier> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeTier {
+    pub min_hash_rate: f64,
+    pub basis_points: u64,
+}
+
+#[derive(Debug)]
+pub struct InvalidFeePolicy(pub String);
+
+impl FeePolicy {
+    pub fn from_env() -> Self {
+        if let Ok(policy) = std::env::var("POOL_FEE_POLICY") {
+            return policy.parse().expect("POOL_FEE_POLICY is invalid");
+        }
+
+        let default_fee: u64 = 25000000;
+        let amount: u64 = std::env::var("POOL_FIXED_FEE")
+            .map(|s| s.parse().unwrap_or(default_fee))
+            .unwrap_or(default_fee);
+
+        FeePolicy::Fixed { amount }
+    }
+
+    /// The percentage fee for a miner hashing at `hash_rate`, in basis points. Fixed fees have none.
+    pub fn basis_points(&self, hash_rate: f64) -> Option<u64> {
+        match self {
+            FeePolicy::Fixed { .. } => None,
+            FeePolicy::Percent { basis_points } => Some(*basis_points),
+            FeePolicy::Tiered { tiers } => tiers.iter()
+                .rev()
+                .find(|tier| hash_rate >= tier.min_hash_rate)
+                .or(tiers.first())
+                .map(|tier| tier.basis_points),
+        }
+    }
+}
+
+fn parse_percent(percent: &str) -> Result<u64, InvalidFeePolicy> {
+    let percent: f64 = percent.trim().parse()
+        .map_err(|_| InvalidFeePolicy(format!("{} is not a percentage", percent)))?;
+
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(InvalidFeePolicy(format!("{} is not between 0 and 100 percent", percent)));
+    }
+
+    Ok((percent * 100.0).round() as u64)
+}
+
+impl FromStr for FeePolicy {
+    type Err = InvalidFeePolicy;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = policy.trim().split_once(':')
+            .ok_or_else(|| InvalidFeePolicy(format!("{} should look like fixed:, percent: or tiered:", policy)))?;
+
+        match kind {
+            "fixed" => {
+                let amount: u64 = value.trim().parse()
+                    .map_err(|_| InvalidFeePolicy(format!("{} is not an amount of TUNA", value)))?;
+
+                Ok(FeePolicy::Fixed { amount })
+            }
+            "percent" => Ok(FeePolicy::Percent { basis_points: parse_percent(value)? }),
+            "tiered" => {
+                let mut tiers: Vec<FeeTier> = Vec::new();
+                for tier in value.split(',') {
+                    let (min_hash_rate, percent) = tier.split_once('=')
+                        .ok_or_else(|| InvalidFeePolicy(format!("tier {} should look like hash_rate=percent", tier)))?;
+
+                    let min_hash_rate: f64 = min_hash_rate.trim().parse()
+                        .ok()
+                        .filter(|hash_rate: &f64| hash_rate.is_finite() && *hash_rate >= 0.0)
+                        .ok_or_else(|| InvalidFeePolicy(format!("{} is not a hash rate", min_hash_rate)))?;
+
+                    tiers.push(FeeTier { min_hash_rate, basis_points: parse_percent(percent)? });
+                }
+
+                tiers.sort_by(|a, b| a.min_hash_rate.total_cmp(&b.min_hash_rate));
+
+                Ok(FeePolicy::Tiered { tiers })
+            }
+            _ => Err(InvalidFeePolicy(format!("unknown fee policy {}", kind))),
+        }
+    }
+}
+
+impl fmt::Display for FeePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeePolicy::Fixed { amount } => write!(f, "fixed:{}", amount),
+            FeePolicy::Percent { basis_points } => write!(f, "percent:{}", *basis_points as f64 / 100.0),
+            FeePolicy::Tiered { tiers } => {
+                let tiers: Vec<String> = tiers.iter()
+                    .map(|tier| format!("{}={}", tier.min_hash_rate, tier.basis_points as f64 / 100.0))
+                    .collect();
+
+                write!(f, "tiered:{}", tiers.join(","))
+            }
+        }
+    }
+}
+
+/// The pool's fee policy along with everything needed to apply it to a particular miner.
+#[derive(Debug, Clone)]
+pub struct FeeSchedule {
+    pub default_policy: FeePolicy,
+    pub overrides: HashMap<i32, FeePolicy>,
+    pub hash_rates: HashMap<i32, f64>,
+}
+
+impl FeeSchedule {
+    /// The policy that applies to the miner, and whether it is an override of the pool's own.
+    pub fn policy_for(&self, miner_id: i32) -> (&FeePolicy, bool) {
+        match self.overrides.get(&miner_id) {
+            Some(policy) => (policy, true),
+            None => (&self.default_policy, false),
+        }
+    }
+
+    pub fn hash_rate(&self, miner_id: i32) -> f64 {
+        self.hash_rates.get(&miner_id).copied().unwrap_or(0.0)
+    }
+}
+
+pub async fn load_fee_schedule(pool: &Pool<Postgres>) -> Result<FeeSchedule, sqlx::Error> {
+    let mut overrides: HashMap<i32, FeePolicy> = HashMap::new();
+    for fee_override in get_fee_overrides(pool).await? {
+        match fee_override.policy.parse() {
+            Ok(policy) => {
+                overrides.insert(fee_override.miner_id, policy);
+            }
+            Err(e) => log::error!("Ignoring invalid fee override for miner {}: {:?}", fee_override.miner_id, e),
+        }
+    }
+
+    let end_time = Utc::now().naive_utc();
+    let start_time = end_time - Duration::seconds(TIER_HASH_RATE_WINDOW_SECONDS);
+
+    let mut hash_rates: HashMap<i32, f64> = HashMap::new();
+    for proof_count in count_by_time_range(pool, None, start_time, end_time).await? {
+        let estimated_hashes = estimate_hashes_for_difficulty(
+            proof_count.proof_count as usize,
+            proof_count.sampling_difficulty as u8,
+        );
+
+        *hash_rates.entry(proof_count.miner_id).or_insert(0.0) += estimated_hashes / TIER_HASH_RATE_WINDOW_SECONDS as f64;
+    }
+
+    Ok(FeeSchedule {
+        default_policy: FeePolicy::from_env(),
+        overrides,
+        hash_rates,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policies_round_trip() {
+        for policy in ["fixed:25000000", "percent:0.5", "percent:12.34", "tiered:0=1,1000000000=0.5"] {
+            let parsed: FeePolicy = policy.parse().unwrap();
+
+            assert_eq!(parsed.to_string(), policy);
+            assert_eq!(parsed.to_string().parse::<FeePolicy>().unwrap(), parsed);
+        }
+    }
+
+    #[test]
+    fn tiers_are_sorted_by_hash_rate() {
+        let policy: FeePolicy = "tiered: 1000=0.5, 0=1".parse().unwrap();
+
+        assert_eq!(policy.to_string(), "tiered:0=1,1000=0.5");
+        assert_eq!(policy.basis_points(0.0), Some(100));
+        assert_eq!(policy.basis_points(999.0), Some(100));
+        assert_eq!(policy.basis_points(1000.0), Some(50));
+    }
+
+    #[test]
+    fn miners_below_every_tier_pay_the_lowest() {
+        let policy: FeePolicy = "tiered:100=2,200=1".parse().unwrap();
+
+        assert_eq!(policy.basis_points(5.0), Some(200));
+    }
+
+    #[test]
+    fn malformed_policies_are_rejected() {
+        for policy in [
+            "",
+            "25000000",
+            "flat:1",
+            "fixed:",
+            "fixed:-1",
+            "fixed:1.5",
+            "percent:abc",
+            "percent:-1",
+            "percent:100.01",
+            "percent:NaN",
+            "tiered:",
+            "tiered:0",
+            "tiered:0=1,",
+            "tiered:0=1,1000",
+            "tiered:x=1",
+            "tiered:-5=1",
+            "tiered:inf=1",
+            "tiered:NaN=1",
+            "tiered:0=101",
+            "tiered:0=1=2",
+        ] {
+            assert!(policy.parse::<FeePolicy>().is_err(), "{} should be rejected", policy);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+// Process-wide counters for `GET /metrics`. They start from zero whenever tunapond restarts, which
+// Prometheus expects of counters.
+
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    const fn new() -> Self {
+        Counter(AtomicU64::new(0))
+    }
+
+    pub fn inc_by(&self, amount: u64) {
+        self.0.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A counter split by one label. Only label values that have been counted are reported.
+pub struct LabelledCounter(Mutex<BTreeMap<&'static str, u64>>);
+
+impl LabelledCounter {
+    const fn new() -> Self {
+        LabelledCounter(Mutex::new(BTreeMap::new()))
+    }
+
+    pub fn inc_by(&self, label: &'static str, amount: u64) {
+        if let Ok(mut counts) = self.0.lock() {
+            *counts.entry(label).or_insert(0) += amount;
+        }
+    }
+
+    pub fn inc(&self, label: &'static str) {
+        self.inc_by(label, 1);
+    }
+
+    pub fn get(&self) -> Vec<(&'static str, u64)> {
+        self.0.lock()
+            .map(|counts| counts.iter().map(|(label, count)| (*label, *count)).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Upper bounds, in seconds, of the submit latency histogram's buckets.
+pub const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+pub struct Histogram {
+    // Not cumulative; each observation lands in the first bucket it fits, or in none if it is over them all.
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+pub struct HistogramSnapshot {
+    /// Cumulative, as Prometheus expects, paired with each bucket's upper bound.
+    pub buckets: Vec<(f64, u64)>,
+    pub count: u64,
+    pub sum_seconds: f64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Histogram {
+            buckets: [const { AtomicU64::new(0) }; LATENCY_BUCKETS.len()],
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(index) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = LATENCY_BUCKETS.iter()
+            .zip(self.buckets.iter())
+            .map(|(bound, bucket)| {
+                cumulative += bucket.load(Ordering::Relaxed);
+                (*bound, cumulative)
+            })
+            .collect();
+
+        HistogramSnapshot {
+            buckets,
+            count: self.count.load(Ordering::Relaxed),
+            sum_seconds: self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+        }
+    }
+}
+
+pub static SHARES_ACCEPTED: Counter = Counter::new();
+/// By `ShareRejectionReason::name`.
+pub static SHARES_REJECTED: LabelledCounter = LabelledCounter::new();
+pub static SUBMIT_LATENCY: Histogram = Histogram::new();
+pub static RATE_LIMITED_SUBMISSIONS: Counter = Counter::new();
+pub static KUPO_REQUEST_ERRORS: Counter = Counter::new();
+/// Shares good enough to be a new block, by whether their datum reached the submission server.
+pub static CANDIDATE_SUBMISSIONS: LabelledCounter = LabelledCounter::new();
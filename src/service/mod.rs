@@ -1,3 +1,19 @@
 pub mod block;
 pub mod proof_of_work;
-pub mod submission;
\ No newline at end of file
+pub mod submission;
+pub mod audit;
+pub mod reward;
+pub mod payout;
+pub mod bank;
+pub mod reconciliation;
+pub mod withdrawal;
+pub mod fee;
+pub mod signature;
+pub mod payout_address;
+pub mod payout_split;
+pub mod simulation;
+pub mod share_aggregate;
+pub mod network;
+pub mod metrics;
+pub mod webhook;
+pub mod solo_mode;
@@ -0,0 +1,71 @@
+use serde::Serialize;
+use sqlx::{Pool, Postgres};
+
+use crate::{model::block, routes::hashrate::expected_hashes_for_target};
+
+use super::block::Block;
+
+/// How many of the latest blocks the network's hash rate is estimated from.
+const NETWORK_STATS_BLOCKS: i64 = 50;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkStats {
+    pub blocks_sampled: usize,
+    pub estimated_hash_rate: f64,
+    pub average_block_seconds: f64,
+    /// The pool's hash rate over the network's. Both are estimates, so this can briefly read above 1.
+    pub pool_share: f64,
+    /// How long the network is expected to take to find the next block at its current difficulty.
+    pub expected_seconds_to_block: f64,
+    /// How long the pool is expected to take to find the next block itself, if it is hashing at all.
+    pub expected_seconds_to_pool_block: Option<f64>,
+}
+
+pub async fn get_network_stats(pool: &Pool<Postgres>, pool_hash_rate: f64) -> Result<Option<NetworkStats>, sqlx::Error> {
+    let mut blocks: Vec<Block> = block::get_latest(pool, NETWORK_STATS_BLOCKS)
+        .await?
+        .into_iter()
+        .map(|stored| stored.into())
+        .collect();
+    blocks.sort_by_key(|block| block.block_number);
+
+    Ok(network_stats(&blocks, pool_hash_rate))
+}
+
+// Each block was mined against the target its predecessor set, so the network did that target's expected hashes
+// in the time between the two. Only consecutive blocks are compared, since the pool may have missed some.
+pub fn network_stats(blocks: &[Block], pool_hash_rate: f64) -> Option<NetworkStats> {
+    let mut expected_hashes = 0.0;
+    let mut milliseconds: u64 = 0;
+    let mut intervals: usize = 0;
+
+    for pair in blocks.windows(2) {
+        let (previous, block) = (&pair[0], &pair[1]);
+        if block.block_number != previous.block_number + 1 || block.current_time <= previous.current_time {
+            continue;
+        }
+
+        expected_hashes += expected_hashes_for_target(previous.leading_zeroes, previous.difficulty_number);
+        milliseconds += block.current_time - previous.current_time;
+        intervals += 1;
+    }
+
+    if intervals == 0 {
+        return None;
+    }
+
+    let seconds = milliseconds as f64 / 1000.0;
+    let estimated_hash_rate = expected_hashes / seconds;
+
+    let latest = blocks.last()?;
+    let next_block_hashes = expected_hashes_for_target(latest.leading_zeroes, latest.difficulty_number);
+
+    Some(NetworkStats {
+        blocks_sampled: intervals,
+        estimated_hash_rate,
+        average_block_seconds: seconds / intervals as f64,
+        pool_share: pool_hash_rate / estimated_hash_rate,
+        expected_seconds_to_block: next_block_hashes / estimated_hash_rate,
+        expected_seconds_to_pool_block: (pool_hash_rate > 0.0).then(|| next_block_hashes / pool_hash_rate),
+    })
+}
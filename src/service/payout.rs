@@ -0,0 +1,530 @@
+use std::{cmp::Reverse, collections::HashMap, fmt};
+
+use serde::Serialize;
+
+use crate::{
+    address::{pkh_from_address, AddressParseError},
+    model::payout_split::PayoutSplit,
+};
+
+use super::{
+    fee::{FeePolicy, FeeSchedule},
+    payout_split::TOTAL_BASIS_POINTS,
+    reward::MinerWeight,
+};
+
+pub const TUNA_PER_DATUM: u64 = 5_000_000_000;
+
+#[derive(Debug, Clone)]
+pub struct PayoutConfig {
+    pub finders_fee: u64,
+}
+
+impl PayoutConfig {
+    pub fn from_env() -> Self {
+        let default_finders_fee: u64 = 20000000;
+        let finders_fee: u64 = std::env::var("POOL_FINDERS_FEE")
+            .map(|s| s.parse().unwrap_or(default_finders_fee))
+            .unwrap_or(default_finders_fee);
+
+        PayoutConfig { finders_fee }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MinerPayout {
+    pub miner_id: i32,
+    pub address: String,
+    pub share_count: i64,
+    pub estimated_hashes: f64,
+    /// What the pool kept of this miner's part of the reward.
+    pub pool_fee: u64,
+    pub finders_fee: u64,
+    /// What the miner is credited, including any finder's fee.
+    pub amount: u64,
+    /// The fee policy `pool_fee` was charged under.
+    pub fee_policy: String,
+    pub fee_override: bool,
+    /// The miner's share of their payout this address received, when their rewards are split.
+    pub split_basis_points: Option<i32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PayoutPlan {
+    pub payouts: Vec<MinerPayout>,
+    pub pool_fee: u64,
+    /// Reward with nobody to pay it to yet, i.e. the finder's fee when no block has been found.
+    pub unassigned: u64,
+}
+
+/// The miner whose share became the new block.
+#[derive(Debug, Clone)]
+pub struct Finder {
+    pub miner_id: i32,
+    pub address: String,
+}
+
+#[derive(Debug)]
+pub enum PayoutError {
+    FeesExceedReward { fees: u64 },
+    InvariantViolated { paid: u64, expected: u64 },
+}
+
+impl fmt::Display for PayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayoutError::FeesExceedReward { fees } => write!(f, "fees of {} exceed the reward of {}", fees, TUNA_PER_DATUM),
+            PayoutError::InvariantViolated { paid, expected } => write!(f, "paid out {} of a reward of {}", paid, expected),
+        }
+    }
+}
+
+/// Splits `TUNA_PER_DATUM` between the pool and its miners using only integer arithmetic.
+/// The reward, less the finder's fee, is shared out by weight, then each miner's pool fee is
+/// taken from their part under the policy that applies to them. Every unit is accounted for:
+/// rounding remainders go to the miners with the largest fractional claims, ties broken by
+/// lowest miner id. Without a finder, the finder's fee is left unassigned.
+pub fn calculate_payouts(
+    config: &PayoutConfig,
+    fee_schedule: &FeeSchedule,
+    weights: &[MinerWeight],
+    finder: Option<&Finder>,
+) -> Result<PayoutPlan, PayoutError> {
+    if config.finders_fee > TUNA_PER_DATUM {
+        return Err(PayoutError::FeesExceedReward { fees: config.finders_fee });
+    }
+
+    let mut weights: Vec<MinerWeight> = weights.to_vec();
+    let finder_id = finder.map(|finder| finder.miner_id);
+
+    // The finder is always paid their fee, even if their shares fell outside the scheme's window.
+    if let Some(finder) = finder {
+        if !weights.iter().any(|weight| weight.miner_id == finder.miner_id) {
+            weights.push(MinerWeight {
+                miner_id: finder.miner_id,
+                miner_address: finder.address.clone(),
+                share_count: 0,
+                estimated_hashes: 0.0,
+                weight: 0.0,
+            });
+        }
+    }
+    weights.sort_by_key(|weight| weight.miner_id);
+
+    // Miners choose their own sampling difficulty, so weights are clamped well clear of u128 overflow.
+    let max_weight = 2f64.powi(100);
+    let mut integer_weights: Vec<u128> = weights
+        .iter()
+        .map(|weight| weight.weight.clamp(0.0, max_weight).round() as u128)
+        .collect();
+
+    // With nothing to weigh by, everything goes to whoever found the block.
+    if integer_weights.iter().all(|weight| *weight == 0) {
+        integer_weights = weights.iter().map(|weight| (Some(weight.miner_id) == finder_id) as u128).collect();
+    }
+
+    let miner_rewards = apportion(TUNA_PER_DATUM - config.finders_fee, &integer_weights);
+
+    // A fixed fee is shared out like the reward, so miners who all pay the same one pay exactly it between them.
+    let mut fixed_fees: HashMap<u64, Vec<u64>> = HashMap::new();
+
+    let payouts: Vec<MinerPayout> = weights
+        .into_iter()
+        .zip(miner_rewards)
+        .enumerate()
+        .map(|(index, (weight, reward))| {
+            let (policy, fee_override) = fee_schedule.policy_for(weight.miner_id);
+            let pool_fee = match policy {
+                FeePolicy::Fixed { amount } => fixed_fees
+                    .entry(*amount)
+                    .or_insert_with(|| apportion(*amount, &integer_weights))[index],
+                _ => percentage_fee(reward, policy.basis_points(fee_schedule.hash_rate(weight.miner_id)).unwrap_or(0)),
+            }.min(reward);
+
+            let finders_fee = if Some(weight.miner_id) == finder_id { config.finders_fee } else { 0 };
+
+            MinerPayout {
+                miner_id: weight.miner_id,
+                address: weight.miner_address,
+                share_count: weight.share_count,
+                estimated_hashes: weight.estimated_hashes,
+                pool_fee,
+                finders_fee,
+                amount: reward - pool_fee + finders_fee,
+                fee_policy: policy.to_string(),
+                fee_override,
+                split_basis_points: None,
+            }
+        })
+        .filter(|payout| payout.amount > 0 || payout.pool_fee > 0)
+        .collect();
+
+    let paid_to_miners: u64 = payouts.iter().map(|payout| payout.amount).sum();
+    let pool_fee: u64 = payouts.iter().map(|payout| payout.pool_fee).sum();
+    let plan = PayoutPlan {
+        payouts,
+        pool_fee,
+        unassigned: TUNA_PER_DATUM - pool_fee - paid_to_miners,
+    };
+
+    if finder.is_some() {
+        verify_payment_total(paid_to_miners, plan.pool_fee)?;
+    }
+
+    Ok(plan)
+}
+
+/// A solo miner's block pays its finder the whole reward, less their pool fee. No shares are
+/// weighed, so the payout carries no share count or estimated hashes.
+pub fn calculate_solo_payout(fee_schedule: &FeeSchedule, finder: &Finder) -> Result<PayoutPlan, PayoutError> {
+    let (policy, fee_override) = fee_schedule.policy_for(finder.miner_id);
+    let pool_fee = match policy {
+        FeePolicy::Fixed { amount } => *amount,
+        _ => percentage_fee(TUNA_PER_DATUM, policy.basis_points(fee_schedule.hash_rate(finder.miner_id)).unwrap_or(0)),
+    };
+
+    if pool_fee > TUNA_PER_DATUM {
+        return Err(PayoutError::FeesExceedReward { fees: pool_fee });
+    }
+
+    let plan = PayoutPlan {
+        payouts: vec![MinerPayout {
+            miner_id: finder.miner_id,
+            address: finder.address.clone(),
+            share_count: 0,
+            estimated_hashes: 0.0,
+            pool_fee,
+            finders_fee: 0,
+            amount: TUNA_PER_DATUM - pool_fee,
+            fee_policy: policy.to_string(),
+            fee_override,
+            split_basis_points: None,
+        }],
+        pool_fee,
+        unassigned: 0,
+    };
+
+    Ok(plan)
+}
+
+/// Shares each payout of a miner with splits between their payees by basis points. The fees and
+/// share counts are split the same way, so the ledger still adds up to exactly what was paid.
+pub fn split_payouts(plan: PayoutPlan, splits: &HashMap<i32, Vec<PayoutSplit>>) -> PayoutPlan {
+    let mut payouts: Vec<MinerPayout> = Vec::with_capacity(plan.payouts.len());
+
+    for payout in plan.payouts {
+        let Some(miner_splits) = splits.get(&payout.miner_id).filter(|miner_splits| !miner_splits.is_empty()) else {
+            payouts.push(payout);
+            continue;
+        };
+
+        let weights: Vec<u128> = miner_splits.iter().map(|split| split.basis_points.max(0) as u128).collect();
+        let amounts = apportion(payout.amount, &weights);
+        let pool_fees = apportion(payout.pool_fee, &weights);
+        let finders_fees = apportion(payout.finders_fee, &weights);
+        let share_counts = apportion(payout.share_count.max(0) as u64, &weights);
+
+        for (index, split) in miner_splits.iter().enumerate() {
+            payouts.push(MinerPayout {
+                miner_id: payout.miner_id,
+                address: split.address.clone(),
+                share_count: share_counts[index] as i64,
+                estimated_hashes: payout.estimated_hashes * split.basis_points as f64 / TOTAL_BASIS_POINTS as f64,
+                pool_fee: pool_fees[index],
+                finders_fee: finders_fees[index],
+                amount: amounts[index],
+                fee_policy: payout.fee_policy.clone(),
+                fee_override: payout.fee_override,
+                split_basis_points: Some(split.basis_points),
+            });
+        }
+    }
+
+    PayoutPlan { payouts, ..plan }
+}
+
+/// What each address is credited in the datum. The bank credits payment key hashes, and a payout address,
+/// a split and a mining address can share one, so payments are added up per key and sent under the first
+/// address paid to it. Otherwise one would overwrite the other in the new bank.
+pub fn payments_by_pkh(payouts: &[MinerPayout]) -> Result<HashMap<String, u64>, AddressParseError> {
+    let mut payments: HashMap<String, (String, u64)> = HashMap::new();
+    for payout in payouts.iter().filter(|payout| payout.amount > 0) {
+        let (_, amount) = payments.entry(pkh_from_address(&payout.address)?)
+            .or_insert_with(|| (payout.address.clone(), 0));
+        *amount += payout.amount;
+    }
+
+    Ok(payments.into_values().collect())
+}
+
+fn percentage_fee(reward: u64, basis_points: u64) -> u64 {
+    (reward as u128 * basis_points.min(10_000) as u128 / 10_000) as u64
+}
+
+/// Every unit of the datum's reward must be either paid to a miner or kept as the pool fee.
+pub fn verify_payment_total(paid_to_miners: u64, pool_fee: u64) -> Result<(), PayoutError> {
+    let paid = paid_to_miners + pool_fee;
+
+    if paid != TUNA_PER_DATUM {
+        return Err(PayoutError::InvariantViolated { paid, expected: TUNA_PER_DATUM });
+    }
+
+    Ok(())
+}
+
+/// Largest remainder apportionment of `total` by `weights`. The result always sums to `total`
+/// as long as at least one weight is non-zero.
+fn apportion(total: u64, weights: &[u128]) -> Vec<u64> {
+    // Keep total * weight within a u128 by dropping precision that could never affect the result.
+    let total_weight: u128 = weights.iter().sum();
+    let excess_bits = (128 - total_weight.leading_zeros()).saturating_sub(64);
+    let weights: Vec<u128> = weights.iter().map(|weight| weight >> excess_bits).collect();
+
+    let total_weight: u128 = weights.iter().sum();
+    if total_weight == 0 {
+        return vec![0; weights.len()];
+    }
+
+    let mut shares: Vec<u64> = Vec::with_capacity(weights.len());
+    let mut remainders: Vec<(usize, u128)> = Vec::with_capacity(weights.len());
+
+    for (index, weight) in weights.iter().enumerate() {
+        let numerator = total as u128 * weight;
+        shares.push((numerator / total_weight) as u64);
+        remainders.push((index, numerator % total_weight));
+    }
+
+    let undistributed = total - shares.iter().sum::<u64>();
+
+    // sort_by_key is stable, so equal remainders keep their (miner id) order
+    remainders.sort_by_key(|(_, remainder)| Reverse(*remainder));
+    for (index, _) in remainders.into_iter().take(undistributed as usize) {
+        shares[index] += 1;
+    }
+
+    shares
+}
+
+#[cfg(test)]
+mod tests {
+    use cardano_multiplatform_lib as C;
+
+    use super::*;
+
+    fn weight(miner_id: i32, weight: f64) -> MinerWeight {
+        MinerWeight {
+            miner_id,
+            miner_address: format!("addr{}", miner_id),
+            share_count: 1,
+            estimated_hashes: weight,
+            weight,
+        }
+    }
+
+    fn fee_schedule(default_policy: FeePolicy) -> FeeSchedule {
+        FeeSchedule {
+            default_policy,
+            overrides: HashMap::new(),
+            hash_rates: HashMap::new(),
+        }
+    }
+
+    fn total_paid(plan: &PayoutPlan) -> u64 {
+        plan.payouts.iter().map(|payout| payout.amount).sum::<u64>() + plan.pool_fee
+    }
+
+    #[test]
+    fn apportion_gives_remainders_to_largest_fractions() {
+        assert_eq!(apportion(10, &[1, 1, 1]), vec![4, 3, 3]);
+        assert_eq!(apportion(10, &[1, 2, 2]), vec![2, 4, 4]);
+        assert_eq!(apportion(7, &[3, 1]), vec![5, 2]);
+    }
+
+    #[test]
+    fn apportion_always_sums_to_total() {
+        let weights: Vec<u128> = vec![1, 7, 13, 1 << 90, 0, 99_999];
+        let shares = apportion(TUNA_PER_DATUM, &weights);
+
+        assert_eq!(shares.iter().sum::<u64>(), TUNA_PER_DATUM);
+        assert_eq!(shares[4], 0);
+    }
+
+    #[test]
+    fn apportion_without_weight_pays_nobody() {
+        assert_eq!(apportion(100, &[0, 0]), vec![0, 0]);
+        assert_eq!(apportion(100, &[]), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn payouts_account_for_the_whole_reward() {
+        let config = PayoutConfig { finders_fee: 20_000_000 };
+        let finder = Finder { miner_id: 2, address: String::from("addr2") };
+        let weights = vec![weight(1, 3.0), weight(2, 5.0), weight(3, 11.0)];
+
+        for policy in [
+            FeePolicy::Fixed { amount: 25_000_000 },
+            FeePolicy::Percent { basis_points: 133 },
+        ] {
+            let plan = calculate_payouts(&config, &fee_schedule(policy), &weights, Some(&finder)).unwrap();
+
+            assert_eq!(total_paid(&plan), TUNA_PER_DATUM);
+            assert_eq!(plan.unassigned, 0);
+            assert_eq!(plan.payouts.iter().find(|payout| payout.miner_id == 2).unwrap().finders_fee, 20_000_000);
+        }
+    }
+
+    #[test]
+    fn fixed_fee_is_charged_exactly_once() {
+        let config = PayoutConfig { finders_fee: 0 };
+        let finder = Finder { miner_id: 1, address: String::from("addr1") };
+        let weights = vec![weight(1, 1.0), weight(2, 1.0), weight(3, 1.0)];
+
+        let plan = calculate_payouts(&config, &fee_schedule(FeePolicy::Fixed { amount: 25_000_000 }), &weights, Some(&finder)).unwrap();
+
+        assert_eq!(plan.pool_fee, 25_000_000);
+    }
+
+    #[test]
+    fn zero_weights_pay_the_finder() {
+        let config = PayoutConfig { finders_fee: 20_000_000 };
+        let finder = Finder { miner_id: 2, address: String::from("addr2") };
+        let weights = vec![weight(1, 0.0), weight(2, 0.0)];
+
+        let plan = calculate_payouts(&config, &fee_schedule(FeePolicy::Percent { basis_points: 0 }), &weights, Some(&finder)).unwrap();
+
+        assert_eq!(plan.payouts.len(), 1);
+        assert_eq!(plan.payouts[0].miner_id, 2);
+        assert_eq!(plan.payouts[0].amount, TUNA_PER_DATUM);
+    }
+
+    #[test]
+    fn no_weights_pay_the_finder() {
+        let config = PayoutConfig { finders_fee: 20_000_000 };
+        let finder = Finder { miner_id: 4, address: String::from("addr4") };
+
+        let plan = calculate_payouts(&config, &fee_schedule(FeePolicy::Fixed { amount: 25_000_000 }), &[], Some(&finder)).unwrap();
+
+        assert_eq!(plan.payouts.len(), 1);
+        assert_eq!(plan.payouts[0].amount, TUNA_PER_DATUM - 25_000_000);
+        assert_eq!(total_paid(&plan), TUNA_PER_DATUM);
+    }
+
+    #[test]
+    fn no_finder_leaves_the_finders_fee_unassigned() {
+        let config = PayoutConfig { finders_fee: 20_000_000 };
+
+        let plan = calculate_payouts(&config, &fee_schedule(FeePolicy::Percent { basis_points: 0 }), &[weight(1, 1.0)], None).unwrap();
+
+        assert_eq!(plan.unassigned, 20_000_000);
+        assert_eq!(total_paid(&plan) + plan.unassigned, TUNA_PER_DATUM);
+    }
+
+    #[test]
+    fn fees_exceeding_the_reward_are_rejected() {
+        let config = PayoutConfig { finders_fee: TUNA_PER_DATUM + 1 };
+        let finder = Finder { miner_id: 1, address: String::from("addr1") };
+
+        let result = calculate_payouts(&config, &fee_schedule(FeePolicy::Percent { basis_points: 0 }), &[weight(1, 1.0)], Some(&finder));
+        assert!(matches!(result, Err(PayoutError::FeesExceedReward { fees }) if fees == TUNA_PER_DATUM + 1));
+
+        let result = calculate_solo_payout(&fee_schedule(FeePolicy::Fixed { amount: TUNA_PER_DATUM + 1 }), &finder);
+        assert!(matches!(result, Err(PayoutError::FeesExceedReward { .. })));
+    }
+
+    #[test]
+    fn fees_equal_to_the_reward_pay_miners_nothing() {
+        let config = PayoutConfig { finders_fee: 0 };
+        let finder = Finder { miner_id: 1, address: String::from("addr1") };
+        let weights = vec![weight(1, 1.0), weight(2, 2.0)];
+
+        let plan = calculate_payouts(&config, &fee_schedule(FeePolicy::Fixed { amount: TUNA_PER_DATUM * 2 }), &weights, Some(&finder)).unwrap();
+
+        assert_eq!(plan.pool_fee, TUNA_PER_DATUM);
+        assert!(plan.payouts.iter().all(|payout| payout.amount == 0));
+    }
+
+    #[test]
+    fn splits_conserve_amounts_fees_and_shares() {
+        let config = PayoutConfig { finders_fee: 20_000_000 };
+        let finder = Finder { miner_id: 1, address: String::from("addr1") };
+        let weights = vec![
+            MinerWeight { share_count: 7, ..weight(1, 3.0) },
+            MinerWeight { share_count: 13, ..weight(2, 5.0) },
+        ];
+        let plan = calculate_payouts(&config, &fee_schedule(FeePolicy::Percent { basis_points: 133 }), &weights, Some(&finder)).unwrap();
+
+        let splits: HashMap<i32, Vec<PayoutSplit>> = HashMap::from([(1, vec![
+            PayoutSplit { miner_id: 1, address: String::from("payee1"), basis_points: 3333 },
+            PayoutSplit { miner_id: 1, address: String::from("payee2"), basis_points: 3333 },
+            PayoutSplit { miner_id: 1, address: String::from("payee3"), basis_points: 3334 },
+        ])]);
+        let split_plan = split_payouts(plan.clone(), &splits);
+
+        assert_eq!(split_plan.payouts.len(), 4);
+        assert_eq!(split_plan.pool_fee, plan.pool_fee);
+        assert_eq!(total_paid(&split_plan), TUNA_PER_DATUM);
+
+        for miner_id in [1, 2] {
+            let before: Vec<&MinerPayout> = plan.payouts.iter().filter(|payout| payout.miner_id == miner_id).collect();
+            let after: Vec<&MinerPayout> = split_plan.payouts.iter().filter(|payout| payout.miner_id == miner_id).collect();
+
+            assert_eq!(after.iter().map(|payout| payout.amount).sum::<u64>(), before.iter().map(|payout| payout.amount).sum::<u64>());
+            assert_eq!(after.iter().map(|payout| payout.pool_fee).sum::<u64>(), before.iter().map(|payout| payout.pool_fee).sum::<u64>());
+            assert_eq!(after.iter().map(|payout| payout.finders_fee).sum::<u64>(), before.iter().map(|payout| payout.finders_fee).sum::<u64>());
+            assert_eq!(after.iter().map(|payout| payout.share_count).sum::<i64>(), before.iter().map(|payout| payout.share_count).sum::<i64>());
+        }
+    }
+
+    #[test]
+    fn payments_to_the_same_payment_key_are_added_up() {
+        let key_hash = |seed: u8| C::crypto::PrivateKey::from_normal_bytes(&[seed; 32]).unwrap().to_public().hash();
+        let enterprise_address = |seed: u8| C::address::EnterpriseAddress::new(1, &C::address::StakeCredential::from_keyhash(&key_hash(seed)))
+            .to_address()
+            .to_bech32(None)
+            .unwrap();
+        let base_address = C::address::BaseAddress::new(
+            1,
+            &C::address::StakeCredential::from_keyhash(&key_hash(1)),
+            &C::address::StakeCredential::from_keyhash(&key_hash(2)),
+        )
+        .to_address()
+        .to_bech32(None)
+        .unwrap();
+
+        let payout = |miner_id: i32, address: String, amount: u64| MinerPayout {
+            miner_id,
+            address,
+            share_count: 1,
+            estimated_hashes: 1.0,
+            pool_fee: 0,
+            finders_fee: 0,
+            amount,
+            fee_policy: String::from("none"),
+            fee_override: false,
+            split_basis_points: None,
+        };
+        let payouts = vec![
+            payout(1, enterprise_address(1), 300),
+            payout(2, base_address, 200),
+            payout(3, enterprise_address(3), 500),
+            payout(4, enterprise_address(4), 0),
+        ];
+
+        let payments = payments_by_pkh(&payouts).unwrap();
+
+        assert_eq!(payments.len(), 2);
+        assert_eq!(payments.get(&enterprise_address(1)), Some(&500));
+        assert_eq!(payments.get(&enterprise_address(3)), Some(&500));
+        assert!(payments_by_pkh(&[payout(1, String::from("addr1nope"), 1)]).is_err());
+    }
+
+    #[test]
+    fn payment_total_must_match_the_reward() {
+        assert!(verify_payment_total(TUNA_PER_DATUM - 25_000_000, 25_000_000).is_ok());
+        assert!(matches!(
+            verify_payment_total(TUNA_PER_DATUM, 1),
+            Err(PayoutError::InvariantViolated { paid, expected }) if paid == TUNA_PER_DATUM + 1 && expected == TUNA_PER_DATUM
+        ));
+        assert!(verify_payment_total(0, 0).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    address::{pkh_from_address, AddressParseError},
+    model::{
+        miner::get_miner_by_pkh,
+        payout_address::{change, count_changes},
+    },
+};
+
+use super::signature::{verify_signed_message, SignatureError};
+
+#[derive(Debug)]
+pub enum PayoutAddressError {
+    DatabaseError(sqlx::Error),
+    AddressParseError(AddressParseError),
+    SignatureError(SignatureError),
+    UnknownMiner,
+}
+
+impl From<sqlx::Error> for PayoutAddressError {
+    fn from(err: sqlx::Error) -> Self {
+        PayoutAddressError::DatabaseError(err)
+    }
+}
+
+impl From<AddressParseError> for PayoutAddressError {
+    fn from(err: AddressParseError) -> Self {
+        PayoutAddressError::AddressParseError(err)
+    }
+}
+
+impl From<SignatureError> for PayoutAddressError {
+    fn from(err: SignatureError) -> Self {
+        PayoutAddressError::SignatureError(err)
+    }
+}
+
+/// What the mining key signs to authorize a payout address. The change number makes every
+/// signature good for exactly one change, so an old one cannot be replayed.
+pub fn payout_address_message(mining_address: &str, payout_address: &str, change_number: i64) -> String {
+    format!(
+        "tunapond: credit rewards for {} to {} (change {})",
+        mining_address, payout_address, change_number
+    )
+}
+
+pub async fn next_change_number(pool: &Pool<Postgres>, miner_id: i32) -> Result<i64, sqlx::Error> {
+    Ok(count_changes(pool, miner_id).await? + 1)
+}
+
+pub async fn change_payout_address(
+    pool: &Pool<Postgres>,
+    mining_address: &str,
+    payout_address: &str,
+    signature: &str,
+    key: &str,
+) -> Result<(), PayoutAddressError> {
+    let pkh = pkh_from_address(mining_address)?;
+    let miner = get_miner_by_pkh(pool, &pkh).await?.ok_or(PayoutAddressError::UnknownMiner)?;
+
+    // The bank credits a payment key hash, so the payout address must have one.
+    pkh_from_address(payout_address)?;
+
+    let change_number = next_change_number(pool, miner.id).await?;
+    let message = payout_address_message(mining_address, payout_address, change_number);
+    verify_signed_message(mining_address, &miner.pkh, &message, signature, key)?;
+
+    change(
+        pool,
+        miner.id,
+        change_number as i32,
+        miner.payout_address.as_deref(),
+        payout_address,
+        signature,
+        key,
+    )
+    .await?;
+
+    log::info!("Miner {} now has rewards credited to {}.", miner.id, payout_address);
+
+    Ok(())
+}
@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    address::{pkh_from_address, AddressParseError},
+    model::{
+        miner::get_miner_by_pkh,
+        payout_split::{count_changes, replace, PayoutSplit},
+    },
+};
+
+use super::signature::{verify_signed_message, SignatureError};
+
+pub const TOTAL_BASIS_POINTS: i32 = 10_000;
+
+/// Every payee is another entry in the bank datum, so a miner can only split so many ways.
+const MAX_PAYOUT_SPLITS: usize = 10;
+
+#[derive(Debug)]
+pub enum PayoutSplitError {
+    DatabaseError(sqlx::Error),
+    AddressParseError(AddressParseError),
+    SignatureError(SignatureError),
+    InvalidSplits(String),
+    UnknownMiner,
+}
+
+impl From<sqlx::Error> for PayoutSplitError {
+    fn from(err: sqlx::Error) -> Self {
+        PayoutSplitError::DatabaseError(err)
+    }
+}
+
+impl From<AddressParseError> for PayoutSplitError {
+    fn from(err: AddressParseError) -> Self {
+        PayoutSplitError::AddressParseError(err)
+    }
+}
+
+impl From<SignatureError> for PayoutSplitError {
+    fn from(err: SignatureError) -> Self {
+        PayoutSplitError::SignatureError(err)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewPayoutSplit {
+    pub address: String,
+    pub basis_points: i32,
+}
+
+/// The splits as they appear in the signed message, e.g. `addr1...=6000,addr1...=4000`.
+pub fn format_splits(splits: &[NewPayoutSplit]) -> String {
+    splits.iter()
+        .map(|split| format!("{}={}", split.address, split.basis_points))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+pub fn parse_splits(splits: &str) -> Result<Vec<NewPayoutSplit>, PayoutSplitError> {
+    splits.split(',')
+        .filter(|split| !split.is_empty())
+        .map(|split| {
+            let (address, basis_points) = split.split_once('=')
+                .ok_or_else(|| PayoutSplitError::InvalidSplits(format!("{} should look like address=basis_points", split)))?;
+
+            let basis_points: i32 = basis_points.parse()
+                .map_err(|_| PayoutSplitError::InvalidSplits(format!("{} is not a number of basis points", basis_points)))?;
+
+            Ok(NewPayoutSplit { address: address.to_string(), basis_points })
+        })
+        .collect()
+}
+
+/// What the mining key signs to authorize new splits. As with payout addresses, the change number
+/// keeps a signature from being replayed. An empty list of splits removes them.
+pub fn payout_split_message(mining_address: &str, splits: &[NewPayoutSplit], change_number: i64) -> String {
+    format!(
+        "tunapond: split rewards for {} as {} (change {})",
+        mining_address, format_splits(splits), change_number
+    )
+}
+
+pub fn validate_splits(splits: &[NewPayoutSplit]) -> Result<(), PayoutSplitError> {
+    if splits.is_empty() {
+        return Ok(());
+    }
+
+    if splits.len() > MAX_PAYOUT_SPLITS {
+        return Err(PayoutSplitError::InvalidSplits(format!("at most {} splits are allowed", MAX_PAYOUT_SPLITS)));
+    }
+
+    let mut pkhs: HashSet<String> = HashSet::new();
+    for split in splits.iter() {
+        // The bank credits a payment key hash, so every payee must have one, and two addresses with the
+        // same payment key are the same payee.
+        if !pkhs.insert(pkh_from_address(&split.address)?) {
+            return Err(PayoutSplitError::InvalidSplits(format!("{} shares a payment key with another split", split.address)));
+        }
+
+        if split.basis_points <= 0 {
+            return Err(PayoutSplitError::InvalidSplits(format!("{} must be given a positive split", split.address)));
+        }
+    }
+
+    let total: i64 = splits.iter().map(|split| split.basis_points as i64).sum();
+    if total != TOTAL_BASIS_POINTS as i64 {
+        return Err(PayoutSplitError::InvalidSplits(format!(
+            "splits add up to {} basis points, not {}", total, TOTAL_BASIS_POINTS
+        )));
+    }
+
+    Ok(())
+}
+
+pub async fn next_change_number(pool: &Pool<Postgres>, miner_id: i32) -> Result<i64, sqlx::Error> {
+    Ok(count_changes(pool, miner_id).await? + 1)
+}
+
+pub async fn change_payout_splits(
+    pool: &Pool<Postgres>,
+    mining_address: &str,
+    splits: &[NewPayoutSplit],
+    signature: &str,
+    key: &str,
+) -> Result<(), PayoutSplitError> {
+    let pkh = pkh_from_address(mining_address)?;
+    let miner = get_miner_by_pkh(pool, &pkh).await?.ok_or(PayoutSplitError::UnknownMiner)?;
+
+    validate_splits(splits)?;
+
+    let change_number = next_change_number(pool, miner.id).await?;
+    let message = payout_split_message(mining_address, splits, change_number);
+    verify_signed_message(mining_address, &miner.pkh, &message, signature, key)?;
+
+    let payout_splits: Vec<PayoutSplit> = splits.iter()
+        .map(|split| PayoutSplit {
+            miner_id: miner.id,
+            address: split.address.clone(),
+            basis_points: split.basis_points,
+        })
+        .collect();
+
+    replace(pool, miner.id, change_number as i32, &payout_splits, &format_splits(splits), signature, key).await?;
+
+    log::info!("Miner {} now has rewards split as {}.", miner.id, format_splits(splits));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cardano_multiplatform_lib as C;
+
+    use super::*;
+
+    fn address(seed: u8) -> String {
+        let key_hash = C::crypto::PrivateKey::from_normal_bytes(&[seed; 32]).unwrap().to_public().hash();
+        let credential = C::address::StakeCredential::from_keyhash(&key_hash);
+
+        C::address::EnterpriseAddress::new(1, &credential).to_address().to_bech32(None).unwrap()
+    }
+
+    fn split(seed: u8, basis_points: i32) -> NewPayoutSplit {
+        NewPayoutSplit { address: address(seed), basis_points }
+    }
+
+    fn invalid(splits: &[NewPayoutSplit]) -> bool {
+        matches!(validate_splits(splits), Err(PayoutSplitError::InvalidSplits(_)))
+    }
+
+    #[test]
+    fn splits_must_add_up_to_every_basis_point() {
+        assert!(validate_splits(&[split(1, 6000), split(2, 4000)]).is_ok());
+        assert!(validate_splits(&[split(1, TOTAL_BASIS_POINTS)]).is_ok());
+        assert!(invalid(&[split(1, 6000), split(2, 3999)]));
+        assert!(invalid(&[split(1, 6000), split(2, 4001)]));
+    }
+
+    #[test]
+    fn splits_must_be_positive() {
+        assert!(invalid(&[split(1, 10_001), split(2, -1)]));
+        assert!(invalid(&[split(1, TOTAL_BASIS_POINTS), split(2, 0)]));
+    }
+
+    #[test]
+    fn no_splits_removes_them() {
+        assert!(validate_splits(&[]).is_ok());
+        assert!(parse_splits("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn at_most_ten_splits() {
+        let ten: Vec<NewPayoutSplit> = (1..=10).map(|seed| split(seed, 1000)).collect();
+        assert!(validate_splits(&ten).is_ok());
+
+        let eleven: Vec<NewPayoutSplit> = (1..=11)
+            .map(|seed| split(seed, if seed == 1 { 0 } else { 1000 }))
+            .collect();
+        assert!(invalid(&eleven));
+    }
+
+    #[test]
+    fn addresses_appear_once() {
+        assert!(invalid(&[split(1, 5000), split(1, 5000)]));
+    }
+
+    #[test]
+    fn payment_keys_appear_once() {
+        let payment_key = C::crypto::PrivateKey::from_normal_bytes(&[1; 32]).unwrap().to_public().hash();
+        let stake_key = C::crypto::PrivateKey::from_normal_bytes(&[2; 32]).unwrap().to_public().hash();
+        let base_address = C::address::BaseAddress::new(
+            1,
+            &C::address::StakeCredential::from_keyhash(&payment_key),
+            &C::address::StakeCredential::from_keyhash(&stake_key),
+        )
+        .to_address()
+        .to_bech32(None)
+        .unwrap();
+
+        assert!(invalid(&[split(1, 5000), NewPayoutSplit { address: base_address, basis_points: 5000 }]));
+    }
+
+    #[test]
+    fn payees_need_a_valid_address() {
+        let splits = [NewPayoutSplit { address: String::from("addr1nope"), basis_points: TOTAL_BASIS_POINTS }];
+
+        assert!(matches!(validate_splits(&splits), Err(PayoutSplitError::AddressParseError(_))));
+    }
+
+    #[test]
+    fn splits_round_trip_through_their_message_format() {
+        let splits = vec![split(1, 6000), split(2, 4000)];
+        let parsed = parse_splits(&format_splits(&splits)).unwrap();
+
+        assert_eq!(format_splits(&parsed), format_splits(&splits));
+        assert!(parse_splits("addr1=abc").is_err());
+        assert!(parse_splits("addr1").is_err());
+    }
+}
@@ -1,4 +1,6 @@
+use crate::model::block;
 use crate::model::proof_of_work::{self};
+use crate::model::share_rejection::{self, NewShareRejection};
 use crate::routes::submit::Submission;
 use crate::routes::work::generate_nonce;
 use cardano_multiplatform_lib::error::JsError;
@@ -11,9 +13,11 @@ use cardano_multiplatform_lib::plutus::PlutusList;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use super::block::{Block, BlockService, BlockServiceError, ReadableBlock};
+use super::metrics::{CANDIDATE_SUBMISSIONS, SHARES_ACCEPTED, SHARES_REJECTED};
 use super::submission::submit;
 use super::submission::SubmissionError;
 
@@ -62,6 +66,29 @@ pub struct RawSubmitProofOfWorkResponse {
     pub raw_target_state: String,
 }
 
+/// Why a submitted share was not accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShareRejectionReason {
+    MalformedNonce,
+    InsufficientDifficulty,
+    /// Meets the miner's sampling difficulty against the previous block, but not the current one.
+    Stale,
+    NonceNotAssigned,
+    Duplicate,
+}
+
+impl ShareRejectionReason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShareRejectionReason::MalformedNonce => "malformed_nonce",
+            ShareRejectionReason::InsufficientDifficulty => "insufficient_difficulty",
+            ShareRejectionReason::Stale => "stale",
+            ShareRejectionReason::NonceNotAssigned => "nonce_not_assigned",
+            ShareRejectionReason::Duplicate => "duplicate",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ProcessedSubmissionEntry {
     pub miner_id: i32,
@@ -101,6 +128,7 @@ pub async fn submit_proof_of_work(
     block_service: &Arc<BlockService>,
     miner_id: i32,
     miner_sampling_difficulty: u8,
+    miner_solo: bool,
     submission: &Submission,
 ) -> Result<SubmitProofOfWorkResponse, SubmitProofOfWorkError> {
     let pool_id: u8 = std::env::var("POOL_ID")
@@ -115,42 +143,88 @@ pub async fn submit_proof_of_work(
     let nonce = generate_nonce(miner_id);
     let mut target_state_bytes = block_to_target_state(&current_block, &nonce).to_bytes();
 
-    let valid_samples: Vec<ProcessedSubmissionEntry> = submission
-        .entries
-        .iter()
-        .filter_map(|entry| {
-            let nonce_binding = hex::decode(&entry.nonce).unwrap_or_default();
+    let mut rejections: HashMap<ShareRejectionReason, i32> = HashMap::new();
+    let mut previous_target_state_bytes: Option<Option<Vec<u8>>> = None;
+    let mut valid_samples: Vec<ProcessedSubmissionEntry> = Vec::new();
+
+    for entry in submission.entries.iter() {
+        let nonce_binding = hex::decode(&entry.nonce).unwrap_or_default();
 
-            if nonce_binding.len() != 16 {
-                return None;
+        if nonce_binding.len() != 16 {
+            *rejections.entry(ShareRejectionReason::MalformedNonce).or_insert(0) += 1;
+            continue;
+        }
+        let nonce_bytes: [u8; 16] = nonce_binding.try_into().unwrap();
+
+        let hashed_hash = hash_with_nonce(&mut target_state_bytes, &nonce_bytes);
+
+        let entry_difficulty = get_difficulty(&hashed_hash);
+        if entry_difficulty.leading_zeroes < miner_sampling_difficulty as u128 {
+            // Only looked up once something fails, since most submissions are all for the current block.
+            if previous_target_state_bytes.is_none() {
+                let previous_block: Option<Block> = block::get_by_block_range(
+                        pool, current_block.block_number - 1, current_block.block_number - 1
+                    )
+                    .await?
+                    .into_iter()
+                    .next()
+                    .map(|stored| stored.into());
+
+                previous_target_state_bytes = Some(
+                    previous_block.map(|previous_block| block_to_target_state(&previous_block, &nonce).to_bytes())
+                );
             }
-            let nonce_bytes: [u8; 16] = nonce_binding.try_into().unwrap();
 
-            target_state_bytes[4..20].copy_from_slice(&nonce_bytes);
-            let hashed_data = sha256_digest_as_bytes(&target_state_bytes);
-            let hashed_hash = sha256_digest_as_bytes(&hashed_data);
+            let is_stale = previous_target_state_bytes.as_mut().and_then(|bytes| bytes.as_mut()).is_some_and(|bytes| {
+                get_difficulty(&hash_with_nonce(bytes, &nonce_bytes)).leading_zeroes >= miner_sampling_difficulty as u128
+            });
 
-            let entry_difficulty = get_difficulty(&hashed_hash);
-            if entry_difficulty.leading_zeroes < miner_sampling_difficulty as u128 {
-                return None;
-            }
+            let reason = if is_stale { ShareRejectionReason::Stale } else { ShareRejectionReason::InsufficientDifficulty };
+            *rejections.entry(reason).or_insert(0) += 1;
+            continue;
+        }
 
-            if !verify_nonce(&nonce_bytes, miner_id, pool_id) {
-                return None;
-            }
+        if !verify_nonce(&nonce_bytes, miner_id, pool_id) {
+            *rejections.entry(ShareRejectionReason::NonceNotAssigned).or_insert(0) += 1;
+            continue;
+        }
 
-            return Some(ProcessedSubmissionEntry {
-                miner_id,
-                block_number: current_block.block_number,
-                nonce: nonce_bytes,
-                sha: hashed_hash,
-                sampling_difficulty: miner_sampling_difficulty,
-            });
-        })
-        .collect();
+        valid_samples.push(ProcessedSubmissionEntry {
+            miner_id,
+            block_number: current_block.block_number,
+            nonce: nonce_bytes,
+            sha: hashed_hash,
+            sampling_difficulty: miner_sampling_difficulty,
+        });
+    }
+
+    let num_accepted = proof_of_work::create(
+        pool, miner_id, current_block.block_number, &valid_samples, miner_solo, submission.worker.as_deref()
+    ).await?;
+    SHARES_ACCEPTED.inc_by(num_accepted);
+
+    let num_duplicates = valid_samples.len() as i32 - num_accepted as i32;
+    if num_duplicates > 0 {
+        rejections.insert(ShareRejectionReason::Duplicate, num_duplicates);
+    }
+
+    for (reason, share_count) in rejections.iter() {
+        SHARES_REJECTED.inc_by(reason.name(), *share_count as u64);
+    }
 
-    let num_accepted =
-        proof_of_work::create(pool, miner_id, current_block.block_number, &valid_samples).await?;
+    if !rejections.is_empty() {
+        let new_rejections: Vec<NewShareRejection> = rejections
+            .into_iter()
+            .map(|(reason, share_count)| NewShareRejection { reason: reason.name().to_string(), share_count })
+            .collect();
+
+        // Rejections are only kept for statistics, so failing to record them does not fail the submission.
+        if let Err(e) = share_rejection::create(
+            pool, miner_id, submission.worker.as_deref(), current_block.block_number, &new_rejections
+        ).await {
+            log::error!("Failed to record rejected shares for miner {}: {:?}", miner_id, e);
+        }
+    }
 
     let maybe_found_block = valid_samples.iter().find(|sample| {
         let entry_difficulty = get_difficulty(&sample.sha);
@@ -171,7 +245,13 @@ pub async fn submit_proof_of_work(
 
     match maybe_found_block {
         Some(entry) => {
-            let _ = submit(pool, &current_block, miner_id, &entry.sha, &entry.nonce).await;
+            match submit(pool, &current_block, miner_id, miner_solo, &entry.sha, &entry.nonce).await {
+                Ok(()) => CANDIDATE_SUBMISSIONS.inc("submitted"),
+                Err(e) => {
+                    CANDIDATE_SUBMISSIONS.inc("failed");
+                    log::error!("Failed to submit new block {}: {}", hex::encode(entry.sha), e);
+                }
+            }
         }
         None => {}
     }
@@ -183,7 +263,14 @@ pub async fn submit_proof_of_work(
     })
 }
 
-fn sha256_digest_as_bytes(data: &[u8]) -> [u8; 32] {
+// The sha of a target state with the nonce written in. The nonce sits at the same offset in every target state.
+fn hash_with_nonce(target_state_bytes: &mut [u8], nonce_bytes: &[u8; 16]) -> [u8; 32] {
+    target_state_bytes[4..20].copy_from_slice(nonce_bytes);
+    let hashed_data = sha256_digest_as_bytes(target_state_bytes);
+    sha256_digest_as_bytes(&hashed_data)
+}
+
+pub fn sha256_digest_as_bytes(data: &[u8]) -> [u8; 32] {
     let mut hasher = Sha256::new();
     hasher.update(data);
     let result = hasher.finalize();
@@ -191,7 +278,7 @@ fn sha256_digest_as_bytes(data: &[u8]) -> [u8; 32] {
     arr
 }
 
-fn verify_nonce(nonce_bytes: &[u8], miner_id: i32, pool_id: u8) -> bool {
+pub fn verify_nonce(nonce_bytes: &[u8], miner_id: i32, pool_id: u8) -> bool {
     if nonce_bytes.len() != 16 {
         return false;
     }
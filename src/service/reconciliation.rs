@@ -0,0 +1,166 @@
+use std::{collections::{HashMap, HashSet}, fmt};
+
+use chrono::{Duration, Utc};
+use sqlx::{Pool, Postgres};
+
+use crate::model::{
+    datum_submission::{get_unreconciled, DatumSubmission},
+    payout::sum_by_transaction,
+    reconciliation::{record, NewDiscrepancy},
+};
+
+use super::{
+    bank::{bank_from_utxo, get_bank_history, previous_bank_utxo, BankError},
+    block::KupoUtxo,
+};
+
+const BANK_NOT_FOUND_GRACE_MINUTES: i64 = 60;
+
+#[derive(Debug)]
+pub enum ReconciliationError {
+    DatabaseError(sqlx::Error),
+    BankError(BankError),
+}
+
+impl fmt::Display for ReconciliationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReconciliationError::DatabaseError(e) => write!(f, "database error: {}", e),
+            ReconciliationError::BankError(e) => write!(f, "could not read the bank: {}", e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for ReconciliationError {
+    fn from(err: sqlx::Error) -> Self {
+        ReconciliationError::DatabaseError(err)
+    }
+}
+
+impl From<BankError> for ReconciliationError {
+    fn from(err: BankError) -> Self {
+        ReconciliationError::BankError(err)
+    }
+}
+
+pub async fn reconciliation_updater(pool: Pool<Postgres>) {
+    let kupo_url = std::env::var("KUPO_URL")
+        .expect("Cannot instantiate reconciliation updater because KUPO_URL is not set.");
+
+    let interval = 300;
+
+    loop {
+        if let Err(e) = reconcile_confirmed_datums(&pool, &kupo_url).await {
+            log::error!("Failed to reconcile confirmed datums with the bank: {}", e);
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+    }
+}
+
+async fn reconcile_confirmed_datums(pool: &Pool<Postgres>, kupo_url: &str) -> Result<(), ReconciliationError> {
+    let unreconciled = get_unreconciled(pool).await?;
+    if unreconciled.is_empty() {
+        return Ok(());
+    }
+
+    // Each datum's bank was created in the slot it confirmed in, so nothing older than the earliest of them is needed.
+    let after_slot = unreconciled.iter()
+        .filter_map(|datum| datum.confirmed_in_slot)
+        .min()
+        .map(|slot| slot as i64 - 1);
+
+    let bank_utxos = get_bank_history(kupo_url, after_slot).await?;
+
+    for datum in unreconciled {
+        let Some(discrepancies) = reconcile_datum(pool, kupo_url, &bank_utxos, &datum).await? else {
+            // Kupo can lag or briefly miss a UTxO, so a missing bank is only a discrepancy once it stays missing.
+            let confirmed_at = datum.confirmed_at.unwrap_or(datum.created_at);
+            if Utc::now().naive_utc() - confirmed_at < Duration::minutes(BANK_NOT_FOUND_GRACE_MINUTES) {
+                log::debug!("Bank for datum {} not found yet, will retry.", datum.transaction_hash);
+                continue;
+            }
+
+            log::warn!(
+                "Bank for datum {} still not found {} minutes after it confirmed.",
+                datum.transaction_hash,
+                BANK_NOT_FOUND_GRACE_MINUTES
+            );
+
+            record(pool, &datum.transaction_hash, &[NewDiscrepancy {
+                pkh: None,
+                expected: None,
+                actual: None,
+                reason: String::from("bank_not_found"),
+            }]).await?;
+            continue;
+        };
+
+        for discrepancy in discrepancies.iter() {
+            log::warn!(
+                "Bank does not match payouts for datum {}: {} for pkh {:?}, expected {:?} but bank changed by {:?}.",
+                datum.transaction_hash,
+                discrepancy.reason,
+                discrepancy.pkh,
+                discrepancy.expected,
+                discrepancy.actual
+            );
+        }
+
+        record(pool, &datum.transaction_hash, &discrepancies).await?;
+    }
+
+    Ok(())
+}
+
+// A datum transaction spends the previous BANK UTxO and creates the next one. The difference between
+// their owners maps must be exactly what the datum's payouts credited to each pkh. Returns None when
+// either bank is not in Kupo's history.
+async fn reconcile_datum(
+    pool: &Pool<Postgres>,
+    kupo_url: &str,
+    bank_utxos: &[KupoUtxo],
+    datum: &DatumSubmission,
+) -> Result<Option<Vec<NewDiscrepancy>>, ReconciliationError> {
+    let new_bank_utxo = bank_utxos.iter()
+        .find(|utxo| utxo.transaction_id == datum.transaction_hash);
+
+    let previous_bank_utxo = new_bank_utxo.and_then(|new_bank_utxo| previous_bank_utxo(bank_utxos, new_bank_utxo));
+
+    let (Some(new_bank_utxo), Some(previous_bank_utxo)) = (new_bank_utxo, previous_bank_utxo) else {
+        return Ok(None);
+    };
+
+    let new_bank = bank_from_utxo(kupo_url, new_bank_utxo.clone()).await?;
+    let previous_bank = bank_from_utxo(kupo_url, previous_bank_utxo.clone()).await?;
+
+    let expected_increments: HashMap<String, i64> = sum_by_transaction(pool, &datum.transaction_hash)
+        .await?
+        .into_iter()
+        .map(|payout| (payout.pkh, payout.amount))
+        .collect();
+
+    let pkhs: HashSet<&String> = new_bank.owners.keys()
+        .chain(previous_bank.owners.keys())
+        .chain(expected_increments.keys())
+        .collect();
+
+    let mut discrepancies: Vec<NewDiscrepancy> = pkhs.into_iter()
+        .filter_map(|pkh| {
+            let expected = expected_increments.get(pkh).copied().unwrap_or(0);
+            let actual = new_bank.owners.get(pkh).copied().unwrap_or(0) as i64
+                - previous_bank.owners.get(pkh).copied().unwrap_or(0) as i64;
+
+            (expected != actual).then(|| NewDiscrepancy {
+                pkh: Some(pkh.clone()),
+                expected: Some(expected),
+                actual: Some(actual),
+                reason: String::from("amount_mismatch"),
+            })
+        })
+        .collect();
+
+    discrepancies.sort_by(|a, b| a.pkh.cmp(&b.pkh));
+
+    Ok(Some(discrepancies))
+}
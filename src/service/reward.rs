@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    model::proof_of_work::{count_by_round, count_latest, score_by_round},
+    routes::hashrate::estimate_hashes_for_difficulty,
+};
+
+pub const DEFAULT_PPLNS_WINDOW: i64 = 100_000;
+pub const DEFAULT_SCORE_HALF_LIFE_SECONDS: f64 = 600.0;
+
+/// How shares are weighed against each other when a datum's reward is split between miners.
+#[derive(Debug, Clone)]
+pub enum RewardScheme {
+    /// Every share in the round, weighted by its estimated hashes.
+    Proportional,
+    /// The last `window` shares before the block was found, weighted by their estimated hashes.
+    Pplns { window: i64 },
+    /// Every share in the round, weighted by its estimated hashes and decayed exponentially
+    /// by its age when the block was found.
+    Score { half_life_seconds: f64 },
+}
+
+impl RewardScheme {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RewardScheme::Proportional => "proportional",
+            RewardScheme::Pplns { .. } => "pplns",
+            RewardScheme::Score { .. } => "score",
+        }
+    }
+
+    /// Panics on a window or half life that can't weigh shares, so a bad config fails at startup.
+    pub fn from_env() -> Self {
+        let scheme = std::env::var("PAYOUT_SCHEME").unwrap_or_default();
+
+        let scheme = match scheme.to_lowercase().as_str() {
+            "pplns" => {
+                let window: i64 = std::env::var("PPLNS_WINDOW")
+                    .map(|s| s.parse().expect("PPLNS_WINDOW must be a number of shares"))
+                    .unwrap_or(DEFAULT_PPLNS_WINDOW);
+
+                RewardScheme::Pplns { window }
+            }
+            "score" => {
+                let half_life_seconds: f64 = std::env::var("SCORE_HALF_LIFE_SECONDS")
+                    .map(|s| s.parse().expect("SCORE_HALF_LIFE_SECONDS must be a number of seconds"))
+                    .unwrap_or(DEFAULT_SCORE_HALF_LIFE_SECONDS);
+
+                RewardScheme::Score { half_life_seconds }
+            }
+            _ => RewardScheme::Proportional,
+        };
+
+        if let Err(e) = scheme.validate() {
+            panic!("{}", e);
+        }
+
+        scheme
+    }
+
+    /// A window of no shares pays nobody, and a half life of zero divides by zero when scoring.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            RewardScheme::Proportional => Ok(()),
+            RewardScheme::Pplns { window } if *window <= 0 => {
+                Err(format!("PPLNS window must be a positive number of shares, got {}", window))
+            }
+            RewardScheme::Score { half_life_seconds } if !half_life_seconds.is_finite() || *half_life_seconds <= 0.0 => {
+                Err(format!("Score half life must be a positive number of seconds, got {}", half_life_seconds))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MinerWeight {
+    pub miner_id: i32,
+    pub miner_address: String,
+    pub share_count: i64,
+    pub estimated_hashes: f64,
+    /// The miner's claim on the reward under the scheme in use. Only meaningful relative to other weights.
+    pub weight: f64,
+}
+
+pub async fn weigh_shares(
+    pool: &Pool<Postgres>,
+    scheme: &RewardScheme,
+    round_id: i32,
+    end_time: NaiveDateTime,
+) -> Result<Vec<MinerWeight>, sqlx::Error> {
+    let mut weights: HashMap<i32, MinerWeight> = HashMap::new();
+
+    // Counts come back per (miner, sampling difficulty), so fold them down to one weight per miner.
+    match scheme {
+        RewardScheme::Proportional | RewardScheme::Pplns { .. } => {
+            let proof_counts = match scheme {
+                RewardScheme::Pplns { window } => count_latest(pool, *window, end_time).await?,
+                _ => count_by_round(pool, round_id).await?,
+            };
+
+            for proof_count in proof_counts {
+                let estimated_hashes = estimate_hashes_for_difficulty(
+                    proof_count.proof_count as usize,
+                    proof_count.sampling_difficulty as u8,
+                );
+
+                let weight = miner_weight(&mut weights, proof_count.miner_id, proof_count.miner_address);
+                weight.share_count += proof_count.proof_count;
+                weight.estimated_hashes += estimated_hashes;
+                weight.weight += estimated_hashes;
+            }
+        }
+        RewardScheme::Score { half_life_seconds } => {
+            let proof_scores = score_by_round(pool, round_id, end_time, *half_life_seconds).await?;
+
+            for proof_score in proof_scores {
+                let hashes_per_proof = estimate_hashes_for_difficulty(1, proof_score.sampling_difficulty as u8);
+
+                let weight = miner_weight(&mut weights, proof_score.miner_id, proof_score.miner_address);
+                weight.share_count += proof_score.proof_count;
+                weight.estimated_hashes += hashes_per_proof * proof_score.proof_count as f64;
+                weight.weight += hashes_per_proof * proof_score.decayed_count;
+            }
+        }
+    }
+
+    Ok(weights.into_values().collect())
+}
+
+fn miner_weight(weights: &mut HashMap<i32, MinerWeight>, miner_id: i32, miner_address: String) -> &mut MinerWeight {
+    weights.entry(miner_id).or_insert(MinerWeight {
+        miner_id,
+        miner_address,
+        share_count: 0,
+        estimated_hashes: 0.0,
+        weight: 0.0,
+    })
+}
@@ -0,0 +1,38 @@
+use chrono::{Duration, Utc};
+use sqlx::{Pool, Postgres};
+
+use crate::model::share_aggregate::{aggregate, bucket_start, get_aggregated_until, get_oldest_share_at};
+
+/// Shares are stamped when they are inserted, so a bucket is left alone for a while after it ends in case
+/// a submission that started inside it has yet to commit.
+const AGGREGATION_DELAY_SECONDS: i64 = 60;
+
+pub async fn share_aggregator(pool: Pool<Postgres>) {
+    let interval = 60;
+
+    loop {
+        if let Err(e) = aggregate_shares(&pool).await {
+            log::error!("Failed to aggregate shares: {:?}", e);
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+    }
+}
+
+// Rolls every bucket that has ended since the watermark into the aggregates. Until this has run once,
+// old proofs are not cleaned up.
+async fn aggregate_shares(pool: &Pool<Postgres>) -> Result<(), sqlx::Error> {
+    let end_time = bucket_start(Utc::now().naive_utc() - Duration::seconds(AGGREGATION_DELAY_SECONDS));
+
+    let start_time = match get_aggregated_until(pool).await? {
+        Some(aggregated_until) if aggregated_until >= end_time => return Ok(()),
+        Some(aggregated_until) => aggregated_until,
+        None => get_oldest_share_at(pool).await?.map(bucket_start).unwrap_or(end_time).min(end_time),
+    };
+
+    aggregate(pool, start_time, end_time).await?;
+
+    log::debug!("Aggregated shares from {} to {}.", start_time, end_time);
+
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use cardano_message_signing::{
+    cbor::CBORValue,
+    utils::{FromBytes, Int, ToBytes},
+    COSEKey, COSESign1, Label,
+};
+use cardano_multiplatform_lib as C;
+
+#[derive(Debug)]
+pub enum SignatureError {
+    MalformedSignature,
+    MalformedKey,
+    AddressMismatch,
+    PayloadMismatch,
+    KeyMismatch,
+    InvalidSignature,
+}
+
+/// Checks a CIP-8 message signed by a wallet's CIP-30 `signData`: a COSE_Sign1 over `expected_message`
+/// from `address`, and the COSE_Key it was signed with. The key must hash to `pkh`.
+pub fn verify_signed_message(
+    address: &str,
+    pkh: &str,
+    expected_message: &str,
+    signature: &str,
+    key: &str,
+) -> Result<(), SignatureError> {
+    let cose_sign1 = hex::decode(signature)
+        .ok()
+        .and_then(|bytes| COSESign1::from_bytes(bytes).ok())
+        .ok_or(SignatureError::MalformedSignature)?;
+
+    let cose_key = hex::decode(key)
+        .ok()
+        .and_then(|bytes| COSEKey::from_bytes(bytes).ok())
+        .ok_or(SignatureError::MalformedKey)?;
+
+    // The signer's address is carried in the protected headers, so it is covered by the signature.
+    let signed_address = cose_sign1.headers()
+        .protected()
+        .deserialized_headers()
+        .header(&Label::new_text(String::from("address")))
+        .and_then(|value| value.as_bytes());
+
+    let address_bytes = C::address::Address::from_bech32(address)
+        .map_err(|_| SignatureError::AddressMismatch)?
+        .to_bytes();
+
+    if signed_address != Some(address_bytes) {
+        return Err(SignatureError::AddressMismatch);
+    }
+
+    if cose_sign1.payload() != Some(expected_message.as_bytes().to_vec()) {
+        return Err(SignatureError::PayloadMismatch);
+    }
+
+    // OKP keys keep their public key under -2, the x coordinate.
+    let public_key = cose_key.header(&Label::new_int(&Int::new_i32(-2)))
+        .as_ref()
+        .and_then(CBORValue::as_bytes)
+        .and_then(|bytes| C::crypto::PublicKey::from_bytes(&bytes).ok())
+        .ok_or(SignatureError::MalformedKey)?;
+
+    if public_key.hash().to_hex() != pkh {
+        return Err(SignatureError::KeyMismatch);
+    }
+
+    let ed25519_signature = C::crypto::Ed25519Signature::from_bytes(cose_sign1.signature())
+        .map_err(|_| SignatureError::MalformedSignature)?;
+
+    let signed_data = cose_sign1.signed_data(None, None)
+        .map_err(|_| SignatureError::MalformedSignature)?
+        .to_bytes();
+
+    if !public_key.verify(&signed_data, &ed25519_signature) {
+        return Err(SignatureError::InvalidSignature);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cardano_message_signing::{
+        builders::{AlgorithmId, COSESign1Builder, EdDSA25519Key},
+        HeaderMap, Headers, ProtectedHeaderMap,
+    };
+
+    use super::*;
+    use crate::service::payout_address::payout_address_message;
+
+    struct Wallet {
+        private_key: C::crypto::PrivateKey,
+        address: String,
+        pkh: String,
+    }
+
+    fn wallet(seed: u8) -> Wallet {
+        let private_key = C::crypto::PrivateKey::from_normal_bytes(&[seed; 32]).unwrap();
+        let key_hash = private_key.to_public().hash();
+        let credential = C::address::StakeCredential::from_keyhash(&key_hash);
+        let address = C::address::EnterpriseAddress::new(1, &credential).to_address().to_bech32(None).unwrap();
+
+        Wallet { private_key, address, pkh: key_hash.to_hex() }
+    }
+
+    // What a CIP-30 wallet returns from signData: a COSE_Sign1 with the address in its protected
+    // headers, and the COSE_Key it was signed with, both hex encoded.
+    fn sign_data(wallet: &Wallet, message: &str) -> (String, String) {
+        let address_bytes = C::address::Address::from_bech32(&wallet.address).unwrap().to_bytes();
+
+        let mut protected = HeaderMap::new();
+        protected.set_algorithm_id(&AlgorithmId::EdDSA.into());
+        protected.set_header(&Label::new_text(String::from("address")), &CBORValue::new_bytes(address_bytes)).unwrap();
+
+        let headers = Headers::new(&ProtectedHeaderMap::new(&protected), &HeaderMap::new());
+        let builder = COSESign1Builder::new(&headers, message.as_bytes().to_vec(), false);
+        let signature = wallet.private_key.sign(&builder.make_data_to_sign().to_bytes());
+        let cose_sign1 = builder.build(signature.to_bytes());
+
+        let cose_key = EdDSA25519Key::new(wallet.private_key.to_public().as_bytes()).build();
+
+        (hex::encode(cose_sign1.to_bytes()), hex::encode(cose_key.to_bytes()))
+    }
+
+    #[test]
+    fn accepts_a_wallet_signature() {
+        let miner = wallet(1);
+        let message = payout_address_message(&miner.address, &wallet(2).address, 1);
+        let (signature, key) = sign_data(&miner, &message);
+
+        assert!(verify_signed_message(&miner.address, &miner.pkh, &message, &signature, &key).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_key_that_is_not_the_miners() {
+        let miner = wallet(1);
+        let other = wallet(3);
+        let message = payout_address_message(&miner.address, &other.address, 1);
+
+        // signed by another wallet, claiming to be the miner's address
+        let (signature, key) = sign_data(&Wallet { address: miner.address.clone(), ..other }, &message);
+
+        assert!(matches!(
+            verify_signed_message(&miner.address, &miner.pkh, &message, &signature, &key),
+            Err(SignatureError::KeyMismatch)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_signature_from_another_address() {
+        let miner = wallet(1);
+        let other = wallet(3);
+        let message = payout_address_message(&miner.address, &other.address, 1);
+        let (signature, key) = sign_data(&other, &message);
+
+        assert!(matches!(
+            verify_signed_message(&miner.address, &miner.pkh, &message, &signature, &key),
+            Err(SignatureError::AddressMismatch)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_signature_made_with_another_key() {
+        let miner = wallet(1);
+        let message = payout_address_message(&miner.address, &wallet(2).address, 1);
+        let (signature, _) = sign_data(&miner, &message);
+        let (_, other_key) = sign_data(&wallet(3), &message);
+
+        assert!(verify_signed_message(&miner.address, &miner.pkh, &message, &signature, &other_key).is_err());
+    }
+
+    #[test]
+    fn rejects_a_replayed_signature() {
+        let miner = wallet(1);
+        let payout_address = wallet(2).address;
+        let (signature, key) = sign_data(&miner, &payout_address_message(&miner.address, &payout_address, 1));
+
+        let next_change = payout_address_message(&miner.address, &payout_address, 2);
+        assert!(matches!(
+            verify_signed_message(&miner.address, &miner.pkh, &next_change, &signature, &key),
+            Err(SignatureError::PayloadMismatch)
+        ));
+
+        let other_payout_address = payout_address_message(&miner.address, &wallet(3).address, 1);
+        assert!(matches!(
+            verify_signed_message(&miner.address, &miner.pkh, &other_payout_address, &signature, &key),
+            Err(SignatureError::PayloadMismatch)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_tampered_signature() {
+        let miner = wallet(1);
+        let message = payout_address_message(&miner.address, &wallet(2).address, 1);
+        let (signature, key) = sign_data(&miner, &message);
+
+        // flip a bit in the last byte, which is part of the ed25519 signature
+        let mut bytes = hex::decode(&signature).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+
+        assert!(matches!(
+            verify_signed_message(&miner.address, &miner.pkh, &message, &hex::encode(bytes), &key),
+            Err(SignatureError::InvalidSignature)
+        ));
+        assert!(matches!(
+            verify_signed_message(&miner.address, &miner.pkh, &message, "nothex", &key),
+            Err(SignatureError::MalformedSignature)
+        ));
+    }
+}
@@ -0,0 +1,152 @@
+use std::{collections::HashMap, fmt};
+
+use sqlx::{Pool, Postgres};
+
+use crate::model::datum_submission::{get_rounds_by_block_range, DatumRound};
+
+use super::{
+    fee::load_fee_schedule,
+    payout::{calculate_payouts, Finder, PayoutConfig, PayoutError},
+    reward::{weigh_shares, RewardScheme},
+};
+
+#[derive(Debug)]
+pub enum SimulationError {
+    DatabaseError(sqlx::Error),
+    PayoutError(PayoutError),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::DatabaseError(e) => write!(f, "database error: {}", e),
+            SimulationError::PayoutError(e) => write!(f, "could not split a reward: {}", e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for SimulationError {
+    fn from(err: sqlx::Error) -> Self {
+        SimulationError::DatabaseError(err)
+    }
+}
+
+impl From<PayoutError> for SimulationError {
+    fn from(err: PayoutError) -> Self {
+        SimulationError::PayoutError(err)
+    }
+}
+
+/// What one miner would have been paid under one scheme.
+#[derive(Debug)]
+pub struct MinerSimulation {
+    pub miner_id: i32,
+    pub address: String,
+    /// One amount per simulated datum, in block order, including the datums that paid this miner nothing.
+    pub amounts: Vec<u64>,
+}
+
+impl MinerSimulation {
+    pub fn total(&self) -> u64 {
+        self.amounts.iter().sum()
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.amounts.is_empty() {
+            return 0.0;
+        }
+
+        self.total() as f64 / self.amounts.len() as f64
+    }
+
+    /// The population variance of the miner's payout per datum.
+    pub fn variance(&self) -> f64 {
+        if self.amounts.is_empty() {
+            return 0.0;
+        }
+
+        let mean = self.mean();
+        self.amounts.iter()
+            .map(|amount| (*amount as f64 - mean).powi(2))
+            .sum::<f64>() / self.amounts.len() as f64
+    }
+}
+
+#[derive(Debug)]
+pub struct SchemeSimulation {
+    pub scheme: RewardScheme,
+    pub pool_fee: u64,
+    pub miners: Vec<MinerSimulation>,
+}
+
+#[derive(Debug)]
+pub struct SimulationReport {
+    pub datums: usize,
+    /// Datums with no round to replay: solo blocks, and blocks found before rounds were recorded.
+    pub skipped: Vec<DatumRound>,
+    pub schemes: Vec<SchemeSimulation>,
+}
+
+/// Replays every pooled datum for a block in the range through each scheme, using the shares still
+/// stored for it. Fees are charged under the current fee schedule, so schemes are compared on the
+/// same footing rather than against whatever fees applied at the time.
+pub async fn simulate(
+    pool: &Pool<Postgres>,
+    schemes: Vec<RewardScheme>,
+    from_block: i32,
+    to_block: i32,
+) -> Result<SimulationReport, SimulationError> {
+    let config = PayoutConfig::from_env();
+    let fee_schedule = load_fee_schedule(pool).await?;
+    let datum_rounds = get_rounds_by_block_range(pool, from_block, to_block).await?;
+
+    let mut skipped: Vec<DatumRound> = Vec::new();
+    let mut simulations: Vec<(RewardScheme, u64, HashMap<i32, MinerSimulation>)> = schemes
+        .into_iter()
+        .map(|scheme| (scheme, 0, HashMap::new()))
+        .collect();
+
+    let mut datums: usize = 0;
+    for datum_round in datum_rounds {
+        let (Some(round_id), Some(ended_at)) = (datum_round.round_id, datum_round.ended_at) else {
+            skipped.push(datum_round);
+            continue;
+        };
+
+        let finder = Finder { miner_id: datum_round.finder_id, address: datum_round.finder_address };
+
+        for (scheme, pool_fee, miners) in simulations.iter_mut() {
+            let miner_weights = weigh_shares(pool, scheme, round_id, ended_at).await?;
+            let payout_plan = calculate_payouts(&config, &fee_schedule, &miner_weights, Some(&finder))?;
+
+            *pool_fee += payout_plan.pool_fee;
+
+            for miner in miners.values_mut() {
+                miner.amounts.push(0);
+            }
+
+            for payout in payout_plan.payouts {
+                let miner = miners.entry(payout.miner_id).or_insert(MinerSimulation {
+                    miner_id: payout.miner_id,
+                    address: payout.address,
+                    amounts: vec![0; datums + 1],
+                });
+                miner.amounts[datums] += payout.amount;
+            }
+        }
+
+        datums += 1;
+    }
+
+    let schemes: Vec<SchemeSimulation> = simulations
+        .into_iter()
+        .map(|(scheme, pool_fee, miners)| {
+            let mut miners: Vec<MinerSimulation> = miners.into_values().collect();
+            miners.sort_by_key(|miner| miner.miner_id);
+
+            SchemeSimulation { scheme, pool_fee, miners }
+        })
+        .collect();
+
+    Ok(SimulationReport { datums, skipped, schemes })
+}
@@ -0,0 +1,91 @@
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    address::{pkh_from_address, AddressParseError},
+    model::{
+        miner::get_miner_by_pkh,
+        solo_mode::{count_changes, request},
+    },
+};
+
+use super::signature::{verify_signed_message, SignatureError};
+
+#[derive(Debug)]
+pub enum SoloModeError {
+    DatabaseError(sqlx::Error),
+    AddressParseError(AddressParseError),
+    SignatureError(SignatureError),
+    UnknownMiner,
+}
+
+impl From<sqlx::Error> for SoloModeError {
+    fn from(err: sqlx::Error) -> Self {
+        SoloModeError::DatabaseError(err)
+    }
+}
+
+impl From<AddressParseError> for SoloModeError {
+    fn from(err: AddressParseError) -> Self {
+        SoloModeError::AddressParseError(err)
+    }
+}
+
+impl From<SignatureError> for SoloModeError {
+    fn from(err: SignatureError) -> Self {
+        SoloModeError::SignatureError(err)
+    }
+}
+
+/// What the mining key signs to switch between solo and pooled mining. Like a payout address change,
+/// the change number makes every signature good for exactly one change.
+pub fn solo_mode_message(mining_address: &str, solo: bool, change_number: i64) -> String {
+    format!(
+        "tunapond: {} mine {} from the next block (change {})",
+        mining_address,
+        if solo { "solo" } else { "in the pool" },
+        change_number
+    )
+}
+
+pub async fn next_change_number(pool: &Pool<Postgres>, miner_id: i32) -> Result<i64, sqlx::Error> {
+    Ok(count_changes(pool, miner_id).await? + 1)
+}
+
+// The change is held until the next block is seen. Applied straight away, a miner holding a winning share
+// could switch to solo just before submitting it, and keep the block from the rest of the pool. Once the
+// chain has moved on, any share they held back is stale.
+pub async fn change_solo_mode(
+    pool: &Pool<Postgres>,
+    mining_address: &str,
+    solo: bool,
+    signature: &str,
+    key: &str,
+) -> Result<(), SoloModeError> {
+    let pkh = pkh_from_address(mining_address)?;
+    let miner = get_miner_by_pkh(pool, &pkh).await?.ok_or(SoloModeError::UnknownMiner)?;
+
+    let change_number = next_change_number(pool, miner.id).await?;
+    let message = solo_mode_message(mining_address, solo, change_number);
+    verify_signed_message(mining_address, &miner.pkh, &message, signature, key)?;
+
+    request(pool, miner.id, change_number as i32, solo, signature, key).await?;
+
+    log::info!("Miner {} will mine {} from the next block.", miner.id, if solo { "solo" } else { "in the pool" });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_names_the_mode_and_change() {
+        let solo = solo_mode_message("addr1", true, 3);
+        let pooled = solo_mode_message("addr1", false, 3);
+
+        assert_eq!(solo, "tunapond: addr1 mine solo from the next block (change 3)");
+        assert_eq!(pooled, "tunapond: addr1 mine in the pool from the next block (change 3)");
+        assert_ne!(solo_mode_message("addr1", true, 4), solo);
+    }
+}
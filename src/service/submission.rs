@@ -1,30 +1,59 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 use cardano_multiplatform_lib::{
     error::JsError,
 };
-use chrono::{Utc};
+use chrono::{NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{ Postgres, Pool};
 
 use crate::{
+    address::{pkh_from_address, AddressParseError},
     model::{
+        block::{self, StoredBlock},
         datum_submission::{
-            self, accept, get_newest_confirmed_datum, get_unconfirmed, reject, DatumSubmission,
+            self, accept, get_unconfirmed, reject, DatumSubmission,
         },
-        proof_of_work::{self, cleanup_old_proofs, count_by_time_range},
+        miner::{get_miner_by_id, get_payout_addresses},
+        payout::{self, NewPayout},
+        payout_split::{self, PayoutSplit},
+        proof_of_work::{cleanup_old_proofs, count_by_round, count_by_time_range},
+        round::{self, Round},
+    },
+    routes::hashrate::{estimate_hashes_for_difficulty, estimate_hashrate, expected_hashes_for_target},
+    service::{
+        fee::load_fee_schedule,
+        metrics::KUPO_REQUEST_ERRORS,
+        payout::{calculate_payouts, calculate_solo_payout, payments_by_pkh, split_payouts, verify_payment_total, Finder, PayoutConfig, PayoutError, PayoutPlan},
+        proof_of_work::get_difficulty,
+        reward::{weigh_shares, MinerWeight, RewardScheme},
+        webhook::{notify, WebhookEvent},
     },
-    routes::hashrate::{estimate_hashes_for_difficulty},
-    service::proof_of_work::get_difficulty,
 };
 
-use super::block::{Block, KupoUtxo};
+use super::block::{fetch_next_block, Block, KupoUtxo};
 
 #[derive(Debug)]
 pub enum SubmissionError {
     DatabaseError(sqlx::Error),
     JsError(JsError),
     ReqwestError(reqwest::Error),
+    PayoutError(PayoutError),
+    AddressParseError(AddressParseError),
+    UnknownMiner(i32),
+}
+
+impl fmt::Display for SubmissionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmissionError::DatabaseError(e) => write!(f, "database error: {}", e),
+            SubmissionError::JsError(e) => write!(f, "could not build the datum: {}", e),
+            SubmissionError::ReqwestError(e) => write!(f, "could not reach the submission server: {}", e),
+            SubmissionError::PayoutError(e) => write!(f, "could not split the reward: {}", e),
+            SubmissionError::AddressParseError(e) => write!(f, "could not pay an address: {}", e),
+            SubmissionError::UnknownMiner(miner_id) => write!(f, "no miner with id {}", miner_id),
+        }
+    }
 }
 
 impl From<sqlx::Error> for SubmissionError {
@@ -45,7 +74,18 @@ impl From<reqwest::Error> for SubmissionError {
     }
 }
 
-const TUNA_PER_DATUM: usize = 5_000_000_000;
+impl From<AddressParseError> for SubmissionError {
+    fn from(err: AddressParseError) -> Self {
+        SubmissionError::AddressParseError(err)
+    }
+}
+
+impl From<PayoutError> for SubmissionError {
+    fn from(err: PayoutError) -> Self {
+        SubmissionError::PayoutError(err)
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct DenoSubmission {
     nonce: String,
@@ -53,7 +93,8 @@ pub struct DenoSubmission {
     current_block: Block,
     new_zeroes: i64,
     new_difficulty: i64,
-    miner_payments: HashMap<String, usize>, // <Address, Payment>
+    miner_payments: HashMap<String, u64>, // <Address, Payment>
+    pool_fee: u64,
     hash_rate: f64,
 }
 #[derive(Deserialize)]
@@ -66,76 +107,223 @@ pub async fn submit(
     pool: &Pool<Postgres>,
     current_block: &Block,
     miner_id: i32,
+    solo: bool,
     sha: &[u8],
     nonce: &[u8],
 ) -> Result<(), SubmissionError> {
-    let new_diff_data = get_difficulty(sha);
+    let finder = get_miner_by_id(pool, miner_id).await?.ok_or(SubmissionError::UnknownMiner(miner_id))?;
+    let finder = Finder { miner_id: finder.id, address: finder.payout_address().to_string() };
 
-    let default_fee: i64 = 25000000;
-    let pool_fixed_fee: i64 = std::env::var("POOL_FIXED_FEE")
-        .map(|s| s.parse().unwrap_or(default_fee))
-        .unwrap_or(default_fee);
-
-    let default_finders_fee: i64 = 20000000;
-    let finders_fee: i64 = std::env::var("POOL_FIXED_FEE")
-        .map(|s| s.parse().unwrap_or(default_finders_fee))
-        .unwrap_or(default_finders_fee);
-
-    let total_payout = TUNA_PER_DATUM - pool_fixed_fee as usize - finders_fee as usize;
-
-    let maybe_last_paid_datum = get_newest_confirmed_datum(pool).await?;
-
-    let start_time = match maybe_last_paid_datum {
-        Some(last_paid_datum) => {
-            let maybe_last_confirmed = last_paid_datum.confirmed_at;
-            if maybe_last_confirmed.is_some() {
-                maybe_last_confirmed.unwrap()
-            } else {
-                proof_of_work::get_oldest(pool)
-                    .await
-                    .unwrap()
-                    .unwrap()
-                    .created_at
-            }
+    if solo {
+        return submit_solo(pool, current_block, &finder, sha, nonce).await;
+    }
+
+    let (estimated_hashes, expected_hashes) = block_effort(pool, current_block).await?;
+    let round = round::cut_open(pool, Utc::now().naive_utc(), &hex::encode(sha), current_block.block_number).await?;
+
+    let (tx_hash, payout_plan) = match submit_round(pool, &round, current_block, &finder, sha, nonce).await {
+        Ok(submitted) => submitted,
+        Err(e) if may_have_been_sent(&e) => {
+            log::error!(
+                "Round {} may have been submitted, so it is left pending until the chain shows whether its datum landed.",
+                round.id
+            );
+            return Err(e);
         }
-        None => {
-            // if no datum has ever been paid before, start from the first proof of work
-            proof_of_work::get_oldest(pool)
-                .await
-                .unwrap()
-                .unwrap()
-                .created_at // invariant upheld by the fact that we have a datum
+        Err(e) => {
+            // nothing went on chain, so the round's shares are still owed
+            round::reject(pool, round.id).await?;
+            return Err(e);
         }
     };
 
-    let end_time = Utc::now().naive_utc();
-    let duration = end_time - start_time;
-    let miner_counts = count_by_time_range(pool, None, start_time, end_time).await?;
+    log::info!("Submitted datum on chain in tx_hash {}", &tx_hash);
 
-    let mut miner_estimated_hashes_map: HashMap<i32, f64> = HashMap::new();
+    // The transaction is recorded before anything else can fail, so the round is never handed back once paid.
+    datum_submission::create(
+        pool,
+        tx_hash.clone(),
+        hex::encode(sha),
+        current_block.block_number,
+        estimated_hashes,
+        expected_hashes,
+    )
+    .await?;
+
+    round::set_transaction_hash(pool, round.id, &tx_hash).await?;
+
+    notify_block_found(pool, current_block, &finder, &tx_hash, sha, false).await;
+
+    record_payouts(pool, &tx_hash, payout_plan, false).await
+}
 
-    // Calculate the total estimated hashes for each miner and the overall total.
-    for proof_detail in &miner_counts {
-        let estimated_hashes = estimate_hashes_for_difficulty(proof_detail.proof_count as usize, proof_detail.sampling_difficulty as u8);
-        *miner_estimated_hashes_map.entry(proof_detail.miner_id).or_insert(0.0) += estimated_hashes;
+// Only the request to the submission server can fail once the datum may be on its way. If it never
+// connected, nothing was sent, but any later failure could have come after the transaction went out.
+fn may_have_been_sent(e: &SubmissionError) -> bool {
+    matches!(e, SubmissionError::ReqwestError(e) if !e.is_connect())
+}
+
+/// Weighs the round's shares under the configured reward scheme and splits a datum's reward by them.
+/// This is exactly what `submit` pays out, so it also backs the payout preview.
+pub async fn plan_round_payouts(
+    pool: &Pool<Postgres>,
+    round_id: i32,
+    end_time: NaiveDateTime,
+    finder: Option<&Finder>,
+) -> Result<(Vec<MinerWeight>, PayoutPlan), SubmissionError> {
+    let mut miner_weights = weigh_shares(pool, &RewardScheme::from_env(), round_id, end_time).await?;
+
+    // Shares are weighed by who mined them, but paid to wherever those miners asked.
+    let payout_addresses = get_payout_addresses(pool).await?;
+    for miner_weight in miner_weights.iter_mut() {
+        if let Some(payout_address) = payout_addresses.get(&miner_weight.miner_id) {
+            miner_weight.miner_address = payout_address.clone();
+        }
     }
 
-    let total_estimated_hashes = miner_estimated_hashes_map.values().sum::<f64>();
-
-    let mut miner_payments: HashMap<String, usize> = HashMap::new();    
-    for proof_detail in &miner_counts {
-        let miner_estimated_hashes = *miner_estimated_hashes_map.get(&proof_detail.miner_id).unwrap_or(&0.0);
-        let miner_share = miner_estimated_hashes / total_estimated_hashes;
-        
-        let miner_payment = (total_payout as f64 * miner_share) as usize;
-        
-        let miner_bonus = if proof_detail.miner_id == miner_id {
-            finders_fee
-        } else {
-            0
-        } as usize;
+    let fee_schedule = load_fee_schedule(pool).await?;
+    let payout_plan = calculate_payouts(&PayoutConfig::from_env(), &fee_schedule, &miner_weights, finder)?;
+    let payout_plan = split_payouts(payout_plan, &load_payout_splits(pool).await?);
 
-        miner_payments.insert(proof_detail.miner_address.clone(), miner_payment + miner_bonus);
+    Ok((miner_weights, payout_plan))
+}
+
+// Splits the round's reward and hands the new datum to the submission server, returning its tx hash.
+async fn submit_round(
+    pool: &Pool<Postgres>,
+    round: &Round,
+    current_block: &Block,
+    finder: &Finder,
+    sha: &[u8],
+    nonce: &[u8],
+) -> Result<(String, PayoutPlan), SubmissionError> {
+    let end_time = round.ended_at.unwrap_or(Utc::now().naive_utc());
+    let hash_rate = round_hash_rate(pool, round, end_time).await?;
+
+    let (_, payout_plan) = plan_round_payouts(pool, round.id, end_time, Some(finder)).await?;
+    let tx_hash = submit_datum(current_block, sha, nonce, &payout_plan, hash_rate).await?;
+
+    Ok((tx_hash, payout_plan))
+}
+
+// A solo block leaves the pooled round open; only its finder is paid.
+async fn submit_solo(
+    pool: &Pool<Postgres>,
+    current_block: &Block,
+    finder: &Finder,
+    sha: &[u8],
+    nonce: &[u8],
+) -> Result<(), SubmissionError> {
+    let now = Utc::now().naive_utc();
+    let hash_rate = match round::get_open(pool).await? {
+        Some(round) => round_hash_rate(pool, &round, now).await?,
+        None => 0.0,
+    };
+    let (estimated_hashes, expected_hashes) = block_effort(pool, current_block).await?;
+
+    let payout_plan = calculate_solo_payout(&load_fee_schedule(pool).await?, finder)?;
+    let payout_plan = split_payouts(payout_plan, &load_payout_splits(pool).await?);
+    let tx_hash = submit_datum(current_block, sha, nonce, &payout_plan, hash_rate).await?;
+
+    log::info!("Submitted solo datum for miner {} on chain in tx_hash {}", finder.miner_id, &tx_hash);
+
+    datum_submission::create(
+        pool,
+        tx_hash.clone(),
+        hex::encode(sha),
+        current_block.block_number,
+        estimated_hashes,
+        expected_hashes,
+    )
+    .await?;
+
+    notify_block_found(pool, current_block, finder, &tx_hash, sha, true).await;
+
+    record_payouts(pool, &tx_hash, payout_plan, true).await
+}
+
+#[derive(Serialize)]
+struct BlockFoundEvent<'a> {
+    block_number: i32,
+    transaction_hash: &'a str,
+    sha: String,
+    finder_id: i32,
+    /// Where the finder is paid, which is their own address unless they set a payout address.
+    finder_address: &'a str,
+    solo: bool,
+}
+
+async fn notify_block_found(
+    pool: &Pool<Postgres>,
+    current_block: &Block,
+    finder: &Finder,
+    tx_hash: &str,
+    sha: &[u8],
+    solo: bool,
+) {
+    notify(pool, WebhookEvent::BlockFound, &BlockFoundEvent {
+        block_number: current_block.block_number,
+        transaction_hash: tx_hash,
+        sha: hex::encode(sha),
+        finder_id: finder.miner_id,
+        finder_address: &finder.address,
+        solo,
+    }).await;
+}
+
+#[derive(Serialize)]
+struct DatumEvent {
+    transaction_hash: String,
+    block_number: i32,
+    confirmed_in_slot: Option<i32>,
+}
+
+pub async fn load_payout_splits(pool: &Pool<Postgres>) -> Result<HashMap<i32, Vec<PayoutSplit>>, sqlx::Error> {
+    let mut splits: HashMap<i32, Vec<PayoutSplit>> = HashMap::new();
+    for split in payout_split::get_all(pool).await? {
+        splits.entry(split.miner_id).or_default().push(split);
+    }
+
+    Ok(splits)
+}
+
+// The round's estimated hashes are reported as the pool's hash rate regardless of how the reward is split.
+async fn round_hash_rate(pool: &Pool<Postgres>, round: &Round, end_time: NaiveDateTime) -> Result<f64, sqlx::Error> {
+    let miner_counts = count_by_round(pool, round.id).await?;
+
+    Ok(estimate_hashrate(miner_counts, round.started_at, end_time))
+}
+
+// The hashes every share, pooled or solo, has put in since the pool's previous datum, and the hashes the
+// block's target was expected to take. Their ratio is the block's effort.
+async fn block_effort(pool: &Pool<Postgres>, current_block: &Block) -> Result<(f64, f64), sqlx::Error> {
+    let since = datum_submission::get_last_created_at(pool).await?.unwrap_or_default();
+    let proof_counts = count_by_time_range(pool, None, since, Utc::now().naive_utc()).await?;
+
+    let estimated_hashes = proof_counts.iter().fold(0.0, |hashes, proof_count| {
+        hashes + estimate_hashes_for_difficulty(proof_count.proof_count as usize, proof_count.sampling_difficulty as u8)
+    });
+    let expected_hashes = expected_hashes_for_target(current_block.leading_zeroes, current_block.difficulty_number);
+
+    Ok((estimated_hashes, expected_hashes))
+}
+
+// Hands the new datum to the submission server, returning its tx hash.
+async fn submit_datum(
+    current_block: &Block,
+    sha: &[u8],
+    nonce: &[u8],
+    payout_plan: &PayoutPlan,
+    hash_rate: f64,
+) -> Result<String, SubmissionError> {
+    let new_diff_data = get_difficulty(sha);
+
+    let miner_payments = payments_by_pkh(&payout_plan.payouts)?;
+
+    // Nothing is sent unless every unit of the reward is accounted for.
+    if let Err(e) = verify_payment_total(miner_payments.values().sum(), payout_plan.pool_fee) {
+        log::error!("Refusing to submit a datum that {}", e);
+        return Err(e.into());
     }
 
     let submission = DenoSubmission {
@@ -144,8 +332,9 @@ pub async fn submit(
         current_block: current_block.clone(),
         new_difficulty: new_diff_data.difficulty_number as i64,
         new_zeroes: new_diff_data.leading_zeroes as i64,
-        miner_payments: miner_payments.clone(),
-        hash_rate: total_estimated_hashes / duration.num_seconds() as f64,
+        miner_payments,
+        pool_fee: payout_plan.pool_fee,
+        hash_rate,
     };
 
     let response: DenoSubmissionResponse = reqwest::Client::new()
@@ -156,15 +345,34 @@ pub async fn submit(
         .json()
         .await?;
 
-    log::info!("Submitted datum on chain in tx_hash {}", &response.tx_hash);
+    Ok(response.tx_hash)
+}
 
-    datum_submission::create(
-        pool,
-        response.tx_hash.clone(),
-        hex::encode(sha),
-        current_block.block_number,
-    )
-    .await?;
+async fn record_payouts(
+    pool: &Pool<Postgres>,
+    tx_hash: &str,
+    payout_plan: PayoutPlan,
+    solo: bool,
+) -> Result<(), SubmissionError> {
+    let mut new_payouts: Vec<NewPayout> = Vec::with_capacity(payout_plan.payouts.len());
+    for payout in payout_plan.payouts {
+        new_payouts.push(NewPayout {
+            miner_id: payout.miner_id,
+            pkh: pkh_from_address(&payout.address)?,
+            address: payout.address,
+            share_count: payout.share_count,
+            estimated_hashes: payout.estimated_hashes,
+            amount: payout.amount as i64,
+            pool_fee: payout.pool_fee as i64,
+            finders_fee: payout.finders_fee as i64,
+            fee_policy: payout.fee_policy,
+            fee_override: payout.fee_override,
+            split_basis_points: payout.split_basis_points,
+            solo,
+        });
+    }
+
+    payout::create(pool, tx_hash, &new_payouts).await?;
 
     Ok(())
 }
@@ -183,6 +391,10 @@ pub async fn submission_updater(pool: Pool<Postgres>) {
     let client = reqwest::Client::new();
 
     loop {
+        if let Err(e) = resolve_abandoned_rounds(&pool, &kupo_url).await {
+            log::error!("Failed to resolve abandoned rounds: {}", e);
+        }
+
         let unconfirmed_datums = get_unconfirmed(&pool).await;
 
         let Ok(unconfirmed) = unconfirmed_datums else {
@@ -191,6 +403,16 @@ pub async fn submission_updater(pool: Pool<Postgres>) {
             continue;
         };
 
+        // Read before Kupo is asked about any datum, so a height past a datum's block means that block was
+        // already taken before Kupo was found not to have the datum.
+        let chain_height = match block::get_height(&pool).await {
+            Ok(chain_height) => chain_height,
+            Err(e) => {
+                log::error!("Submission updater could not fetch the chain height: {:?}", e);
+                None
+            }
+        };
+
         if !unconfirmed.is_empty() {
             for datum in unconfirmed {
                 let url = format!("{}/matches/*@{}", kupo_url, datum.transaction_hash);
@@ -198,6 +420,7 @@ pub async fn submission_updater(pool: Pool<Postgres>) {
                 let resp = client.get(&url).send().await;
 
                 let Ok(r) = resp else {
+                    KUPO_REQUEST_ERRORS.inc();
                     log::warn!(
                         "Failed to fetch matches for transaction_id: {}",
                         datum.transaction_hash
@@ -208,6 +431,7 @@ pub async fn submission_updater(pool: Pool<Postgres>) {
 
                 let response_result: Result<Vec<KupoUtxo>, reqwest::Error> = r.json().await;
                 let Ok(kupo_utxos) = response_result else {
+                    KUPO_REQUEST_ERRORS.inc();
                     log::error!(
                         "Failed to parse kupo transaction! Got {:?}",
                         response_result
@@ -223,6 +447,11 @@ pub async fn submission_updater(pool: Pool<Postgres>) {
                         confirmed_in_slot: Some(slot_no),
                         ..datum
                     };
+                    let datum_event = DatumEvent {
+                        transaction_hash: tx_hash.clone(),
+                        block_number: datum.block_number,
+                        confirmed_in_slot: datum.confirmed_in_slot,
+                    };
                     let result = accept(&pool, vec![datum]).await;
                     log::info!("Permanently accepted datum at transaction {}.", tx_hash);
                     let Ok(_) = result else {
@@ -230,18 +459,42 @@ pub async fn submission_updater(pool: Pool<Postgres>) {
                         tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
                         continue;
                     };
+
+                    if let Err(e) = round::confirm(&pool, &tx_hash).await {
+                        log::error!("Failed to confirm round for transaction_id {}: {:?}", tx_hash, e);
+                    }
+
+                    notify(&pool, WebhookEvent::DatumConfirmed, &datum_event).await;
                 } else {
-                    let now = Utc::now().naive_utc();
-                    let age = now.signed_duration_since(datum.created_at).num_minutes();
-
-                    if age > 2 {
-                        let result = reject(&pool, vec![datum]).await;
-                        let Ok(_) = result else {
-                            log::error!("Failed to reject datum with transaction_id: {}", tx_hash);
-                            tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
-                            continue;
-                        };
+                    // A datum that is slow to land is not lost. It only can't land once another miner's
+                    // block has spent the one it was built on, so until then it stays pending. Blocks can
+                    // be missing from those recorded, so any later block counts, not just the next one.
+                    let Some(chain_height) = chain_height.filter(|chain_height| *chain_height > datum.block_number) else {
+                        continue;
+                    };
+
+                    log::info!(
+                        "Rejecting datum for share {} at transaction {}, the chain reached block {} without it.",
+                        datum.sha, tx_hash, chain_height
+                    );
+
+                    let datum_event = DatumEvent {
+                        transaction_hash: tx_hash.clone(),
+                        block_number: datum.block_number,
+                        confirmed_in_slot: None,
+                    };
+                    let result = reject(&pool, vec![datum]).await;
+                    let Ok(_) = result else {
+                        log::error!("Failed to reject datum with transaction_id: {}", tx_hash);
+                        tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+                        continue;
+                    };
+
+                    if let Err(e) = reject_round(&pool, &tx_hash).await {
+                        log::error!("Failed to reject round for transaction_id {}: {:?}", tx_hash, e);
                     }
+
+                    notify(&pool, WebhookEvent::DatumRejected, &datum_event).await;
                 }
             }
         }
@@ -255,3 +508,86 @@ pub async fn submission_updater(pool: Pool<Postgres>) {
         tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
     }
 }
+
+async fn reject_round(pool: &Pool<Postgres>, transaction_hash: &str) -> Result<(), sqlx::Error> {
+    if let Some(rejected_round) = round::get_by_transaction_hash(pool, transaction_hash).await? {
+        round::reject(pool, rejected_round.id).await?;
+        log::info!("Round {} was rejected, its shares carry over to the open round.", rejected_round.id);
+    }
+
+    Ok(())
+}
+
+// A round is cut before its datum is submitted. If tunapond stops or fails before recording the datum's
+// transaction, the round is resolved from the chain instead: by the datum if it was recorded after all,
+// otherwise by whichever block was mined on the one the round was cut for. Until the chain has passed that
+// block, the datum might still land, so the round stays pending.
+async fn resolve_abandoned_rounds(pool: &Pool<Postgres>, kupo_url: &str) -> Result<(), sqlx::Error> {
+    let cut_before = Utc::now().naive_utc() - chrono::Duration::minutes(10);
+    let chain_height = block::get_height(pool).await?;
+
+    for abandoned_round in round::get_abandoned(pool, cut_before).await? {
+        if let Some(datum) = datum_submission::get_by_sha(pool, &abandoned_round.sha).await? {
+            round::set_transaction_hash(pool, abandoned_round.id, &datum.transaction_hash).await?;
+
+            if datum.confirmed_in_slot.is_some() {
+                round::confirm(pool, &datum.transaction_hash).await?;
+            } else if datum.rejected {
+                round::reject(pool, abandoned_round.id).await?;
+            }
+
+            log::info!("Round {} was linked to its datum at transaction {}.", abandoned_round.id, datum.transaction_hash);
+            continue;
+        }
+
+        if chain_height.filter(|chain_height| *chain_height > abandoned_round.block_number).is_none() {
+            continue;
+        }
+
+        let Some(next_block) = get_next_block(pool, kupo_url, abandoned_round.block_number).await? else {
+            log::error!(
+                "Round {} cannot be resolved until block {} is known.",
+                abandoned_round.id, abandoned_round.block_number + 1
+            );
+            continue;
+        };
+
+        if next_block.current_hash == abandoned_round.sha {
+            round::confirm_unrecorded(pool, abandoned_round.id).await?;
+            log::error!(
+                "Round {} was paid on chain in transaction {}, but its payouts were never recorded.",
+                abandoned_round.id, next_block.transaction_id
+            );
+        } else {
+            round::reject(pool, abandoned_round.id).await?;
+            log::warn!("Round {} was cut but its datum never landed, its shares carry over to the open round.", abandoned_round.id);
+        }
+    }
+
+    Ok(())
+}
+
+// The block after `block_number`. Blocks the updater missed are looked up through Kupo from the one
+// before, and recorded so the gap is filled.
+async fn get_next_block(pool: &Pool<Postgres>, kupo_url: &str, block_number: i32) -> Result<Option<StoredBlock>, sqlx::Error> {
+    if let Some(next_block) = block::get_by_block_number(pool, block_number + 1).await? {
+        return Ok(Some(next_block));
+    }
+
+    let Some(stored_block) = block::get_by_block_number(pool, block_number).await? else {
+        return Ok(None);
+    };
+
+    let next_block = match fetch_next_block(kupo_url, &stored_block).await {
+        Ok(Some(next_block)) => next_block,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            log::error!("Failed to fetch the block after block {}: {:?}", block_number, e);
+            return Ok(None);
+        }
+    };
+
+    block::create(pool, &next_block).await?;
+
+    block::get_by_block_number(pool, block_number + 1).await
+}
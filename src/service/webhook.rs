@@ -0,0 +1,166 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::{Pool, Postgres};
+
+use crate::model::webhook_delivery::{self, WebhookDelivery};
+
+const DISPATCH_INTERVAL_SECONDS: u64 = 5;
+const DELIVERY_BATCH_SIZE: i64 = 50;
+const DELIVERY_TIMEOUT_SECONDS: u64 = 10;
+/// Retries back off from 30 seconds, doubling up to an hour, so the last of these comes about 8 hours in.
+const MAX_DELIVERY_ATTEMPTS: i32 = 12;
+const FIRST_RETRY_SECONDS: i64 = 30;
+const MAX_RETRY_SECONDS: i64 = 3600;
+const DELIVERY_RETENTION_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Copy)]
+pub enum WebhookEvent {
+    BlockSeen,
+    BlockFound,
+    DatumConfirmed,
+    DatumRejected,
+    MinerBanned,
+}
+
+impl WebhookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::BlockSeen => "block.seen",
+            WebhookEvent::BlockFound => "block.found",
+            WebhookEvent::DatumConfirmed => "datum.confirmed",
+            WebhookEvent::DatumRejected => "datum.rejected",
+            WebhookEvent::MinerBanned => "miner.banned",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a, T: Serialize> {
+    /// The same for every url and every retry, so receivers can drop events they have already handled.
+    id: String,
+    event: &'static str,
+    created_at: NaiveDateTime,
+    data: &'a T,
+}
+
+pub fn webhook_urls() -> Vec<String> {
+    std::env::var("WEBHOOK_URLS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect()
+}
+
+pub fn webhook_secret() -> Option<String> {
+    std::env::var("WEBHOOK_SECRET").ok().filter(|secret| !secret.is_empty())
+}
+
+/// Queues `data` for every configured webhook. This only writes to the outbox, so the caller never waits on a
+/// receiver. Failing to queue is logged rather than passed back, since no event is worth failing the caller over.
+pub async fn notify<T: Serialize>(pool: &Pool<Postgres>, event: WebhookEvent, data: &T) {
+    let urls = webhook_urls();
+    if urls.is_empty() {
+        return;
+    }
+
+    let payload = WebhookPayload {
+        id: hex::encode(rand::random::<[u8; 16]>()),
+        event: event.name(),
+        created_at: Utc::now().naive_utc(),
+        data,
+    };
+
+    let payload = match serde_json::to_string(&payload) {
+        Ok(payload) => payload,
+        Err(e) => {
+            log::error!("Failed to serialize {} webhook: {:?}", event.name(), e);
+            return;
+        }
+    };
+
+    if let Err(e) = webhook_delivery::create(pool, &urls, event.name(), &payload).await {
+        log::error!("Failed to queue {} webhook: {:?}", event.name(), e);
+    }
+}
+
+// Hex HMAC-SHA256 of the payload under WEBHOOK_SECRET, sent as `X-Tunapond-Signature: sha256=<signature>`.
+pub fn sign(secret: &str, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(payload.as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// The wait after the given number of failed attempts.
+fn retry_delay(attempts: i32) -> Duration {
+    let doublings = (attempts - 1).clamp(0, 16) as u32;
+
+    Duration::seconds((FIRST_RETRY_SECONDS * 2_i64.pow(doublings)).min(MAX_RETRY_SECONDS))
+}
+
+pub async fn webhook_dispatcher(pool: Pool<Postgres>) {
+    // Checked at startup whenever WEBHOOK_URLS is set, so this is only empty when there is nothing to send.
+    let secret = webhook_secret().unwrap_or_default();
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(DELIVERY_TIMEOUT_SECONDS))
+        .build()
+        .expect("Cannot instantiate webhook dispatcher because the HTTP client failed to build.");
+
+    loop {
+        if let Err(e) = dispatch_due(&pool, &client, &secret).await {
+            log::error!("Failed to dispatch webhooks: {:?}", e);
+        }
+
+        let retain_after = Utc::now().naive_utc() - Duration::days(DELIVERY_RETENTION_DAYS);
+        if let Err(e) = webhook_delivery::cleanup(&pool, retain_after).await {
+            log::error!("Failed to clean up old webhook deliveries: {:?}", e);
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_secs(DISPATCH_INTERVAL_SECONDS)).await;
+    }
+}
+
+async fn dispatch_due(pool: &Pool<Postgres>, client: &reqwest::Client, secret: &str) -> Result<(), sqlx::Error> {
+    for delivery in webhook_delivery::get_due(pool, DELIVERY_BATCH_SIZE).await? {
+        match deliver(client, secret, &delivery).await {
+            Ok(()) => {
+                webhook_delivery::mark_delivered(pool, delivery.id).await?;
+            }
+            Err(error) => {
+                let attempts = delivery.attempts + 1;
+                let next_attempt_at = (attempts < MAX_DELIVERY_ATTEMPTS)
+                    .then(|| Utc::now().naive_utc() + retry_delay(attempts));
+
+                if next_attempt_at.is_none() {
+                    log::warn!("Gave up on webhook delivery {} to {} after {} attempts: {}", delivery.id, delivery.url, attempts, error);
+                }
+
+                webhook_delivery::mark_failed(pool, delivery.id, &error, next_attempt_at).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn deliver(client: &reqwest::Client, secret: &str, delivery: &WebhookDelivery) -> Result<(), String> {
+    let response = client.post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Tunapond-Event", &delivery.event)
+        .header("X-Tunapond-Delivery", delivery.id.to_string())
+        .header("X-Tunapond-Signature", format!("sha256={}", sign(secret, &delivery.payload)))
+        .body(delivery.payload.clone())
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !response.status().is_success() {
+        return Err(format!("Receiver responded with {}", response.status()));
+    }
+
+    Ok(())
+}
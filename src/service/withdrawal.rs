@@ -0,0 +1,100 @@
+use std::fmt;
+
+use sqlx::{Pool, Postgres};
+
+use crate::model::{
+    watermark,
+    withdrawal::{create, NewWithdrawal, WITHDRAWAL_WATERMARK},
+};
+
+use super::bank::{bank_from_utxo, get_bank_history, previous_bank_utxo, BankError};
+
+#[derive(Debug)]
+pub enum WithdrawalError {
+    DatabaseError(sqlx::Error),
+    BankError(BankError),
+}
+
+impl fmt::Display for WithdrawalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WithdrawalError::DatabaseError(e) => write!(f, "database error: {}", e),
+            WithdrawalError::BankError(e) => write!(f, "could not read the bank: {}", e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for WithdrawalError {
+    fn from(err: sqlx::Error) -> Self {
+        WithdrawalError::DatabaseError(err)
+    }
+}
+
+impl From<BankError> for WithdrawalError {
+    fn from(err: BankError) -> Self {
+        WithdrawalError::BankError(err)
+    }
+}
+
+pub async fn withdrawal_watcher(pool: Pool<Postgres>) {
+    let kupo_url = std::env::var("KUPO_URL")
+        .expect("Cannot instantiate withdrawal watcher because KUPO_URL is not set.");
+
+    let interval = 60;
+
+    loop {
+        if let Err(e) = record_withdrawals(&pool, &kupo_url).await {
+            log::error!("Failed to record withdrawals from the bank: {}", e);
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+    }
+}
+
+// Walks every spend of the bank since the watermark. Submitting a datum only ever adds to an owner's
+// balance, so any decrease between one bank and the next is that owner withdrawing.
+async fn record_withdrawals(pool: &Pool<Postgres>, kupo_url: &str) -> Result<(), WithdrawalError> {
+    let watermark = watermark::get(pool, WITHDRAWAL_WATERMARK).await?.unwrap_or(-1);
+
+    // Only banks created after the watermark are new, and each of their predecessors was spent after it.
+    let after_slot = (watermark >= 0).then_some(watermark);
+    let mut bank_utxos = get_bank_history(kupo_url, after_slot).await?;
+    bank_utxos.sort_by_key(|utxo| utxo.created_at.slot_no);
+
+    for bank_utxo in bank_utxos.iter().filter(|utxo| utxo.created_at.slot_no as i64 > watermark) {
+        let Some(previous_utxo) = previous_bank_utxo(&bank_utxos, bank_utxo) else {
+            // The pool's first bank, or one whose predecessor Kupo no longer has.
+            create(pool, &[], bank_utxo.created_at.slot_no).await?;
+            continue;
+        };
+
+        let bank = bank_from_utxo(kupo_url, bank_utxo.clone()).await?;
+        let previous_bank = bank_from_utxo(kupo_url, previous_utxo.clone()).await?;
+
+        let mut new_withdrawals: Vec<NewWithdrawal> = previous_bank.owners.iter()
+            .filter_map(|(pkh, previous_amount)| {
+                let amount = bank.owners.get(pkh).copied().unwrap_or(0);
+
+                (amount < *previous_amount).then(|| NewWithdrawal {
+                    transaction_hash: bank.transaction_id.clone(),
+                    pkh: pkh.clone(),
+                    amount: (previous_amount - amount) as i64,
+                    slot_no: bank.slot_no,
+                })
+            })
+            .collect();
+
+        new_withdrawals.sort_by(|a, b| a.pkh.cmp(&b.pkh));
+
+        for new_withdrawal in new_withdrawals.iter() {
+            log::info!(
+                "{} withdrew {} from the bank in transaction {}.",
+                new_withdrawal.pkh, new_withdrawal.amount, new_withdrawal.transaction_hash
+            );
+        }
+
+        create(pool, &new_withdrawals, bank.slot_no).await?;
+    }
+
+    Ok(())
+}
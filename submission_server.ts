@@ -17,7 +17,6 @@ const KUPO_URL = Deno.env.get("KUPO_URL")!
 const OGMIOS_URL = Deno.env.get("OGMIOS_URL")!
 const POOL_CONTRACT_ADDRESS = Deno.env.get("POOL_CONTRACT_ADDRESS")!
 const POOL_SCRIPT_HASH = Deno.env.get("POOL_SCRIPT_HASH")!
-const POOL_FIXED_FEE = BigInt(Deno.env.get("POOL_FIXED_FEE")!)
 const [POOL_OUTPUT_REF_TX, POOL_OUTPUT_REF_INDEX] = Deno.env.get("POOL_OUTPUT_REFERENCE")!.split("#")
 
 if (!POOL_OUTPUT_REF_TX || !POOL_OUTPUT_REF_INDEX) {
@@ -68,13 +67,14 @@ interface DenoSubmission {
   new_zeroes: number
   new_difficulty: number
   miner_payments: Record<string, number>  // <address, amount>
+  pool_fee: number
   hash_rate: number
 }
 
 
 async function handleSubmit(request: Request): Promise<Response> {
   const answer = await request.json()
-  if (!answer.nonce || !answer.current_block || !answer.new_zeroes || !answer.new_difficulty || !answer.miner_payments) {
+  if (!answer.nonce || !answer.current_block || !answer.new_zeroes || !answer.new_difficulty || !answer.miner_payments || answer.pool_fee === undefined) {
     return new Response(JSON.stringify({
       message: "sent a bad submission"
     }), { status: 400 })
@@ -170,13 +170,10 @@ async function handleSubmitRetrying(answer: DenoSubmission, retries = 0): Promis
   for (const address in answer.miner_payments) {
     const vkh = lucid.utils.paymentCredentialOf(address)!.hash
     const rewardAmount = answer.miner_payments[address]
-    
-    if (bankData.owners.has(vkh)) {
-      const existingAmount = bankData.owners.get(vkh)
-      newBankData.owners.set(vkh, existingAmount! + BigInt(rewardAmount))
-    } else {
-      newBankData.owners.set(vkh, BigInt(rewardAmount))
-    }
+
+    // addresses can share a payment key, so add onto anything this datum already credited to it
+    const existingAmount = newBankData.owners.get(vkh) ?? bankData.owners.get(vkh) ?? 0n
+    newBankData.owners.set(vkh, existingAmount + BigInt(rewardAmount))
   }
 
   newBankData.owners = new Map([...bankData.owners, ...newBankData.owners].sort((a, b) =>  a[0].localeCompare(b[0]) ));
@@ -204,7 +201,7 @@ async function handleSubmitRetrying(answer: DenoSubmission, retries = 0): Promis
         poolRewardAddress,
         { 
           [poolMasterToken]: 1n,
-          [tunaAssetName]: POOL_FIXED_FEE // and give them their cut
+          [tunaAssetName]: BigInt(answer.pool_fee) // and give them their cut
         }
       )
       .payToAddressWithData(